@@ -0,0 +1,91 @@
+//! Borsh round-trip cost of the largest `GreetingAccountState`, and what SetGreeting's
+//! in-place message write saves over re-encoding the whole state.
+//!
+//! Run with `cargo bench --bench state`.
+
+use borsh::BorshDeserialize;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use greeting_program::state::{write_greeting_state, write_message_in_place};
+use greeting_program::GreetingAccountState;
+use solana_program::pubkey::Pubkey;
+
+// Every variable-length field at its limit, with four-byte characters in the message and
+// history, so the encoding is exactly `get_max_space_needed` minus the discriminator.
+fn largest_state() -> GreetingAccountState {
+    let longest_message = "\u{1F44B}".repeat(GreetingAccountState::MAX_MESSAGE_CHARS);
+    GreetingAccountState {
+        version: GreetingAccountState::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        name: "n".repeat(GreetingAccountState::MAX_NAME_LENGTH),
+        message: longest_message.clone(),
+        is_initialized: true,
+        co_authority: Some(Pubkey::new_unique()),
+        expires_at: Some(i64::MAX),
+        history: std::array::from_fn(|_| longest_message.clone()),
+        rent_payer: Pubkey::new_unique(),
+        ..GreetingAccountState::default()
+    }
+}
+
+fn bench_state(c: &mut Criterion) {
+    let state = largest_state();
+    let bytes = borsh::to_vec(&state).unwrap();
+    assert_eq!(
+        GreetingAccountState::ACCOUNT_DISCRIMINATOR.len() + bytes.len(),
+        GreetingAccountState::get_max_space_needed()
+    );
+
+    let mut group = c.benchmark_group("greeting_state_max");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("serialize", |b| {
+        b.iter(|| borsh::to_vec(black_box(&state)).unwrap())
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| GreetingAccountState::try_from_slice(black_box(&bytes)).unwrap())
+    });
+    group.finish();
+}
+
+// One edit of a typical greeting, from the same loaded state and account data each time.
+// The full write re-encodes every field; the in-place one patches the message, history
+// and counters, as SetGreeting does.
+fn bench_message_write(c: &mut Criterion) {
+    let state = GreetingAccountState {
+        version: GreetingAccountState::CURRENT_VERSION,
+        name: "Alice".to_string(),
+        message: "Hello".to_string(),
+        history: ["One", "Two", "Three"].map(str::to_string),
+        ..GreetingAccountState::default()
+    };
+    let mut data = vec![0; GreetingAccountState::get_max_space_needed()];
+    write_greeting_state(&state, &mut data).unwrap();
+
+    let mut group = c.benchmark_group("greeting_message_write");
+    group.bench_function("full_write", |b| {
+        b.iter_batched(
+            || (state.clone(), data.clone()),
+            |(mut state, mut data)| {
+                state.message = "Good morning".to_string();
+                state.update_count += 1;
+                write_greeting_state(&state, &mut data).unwrap();
+                data
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("in_place_write", |b| {
+        b.iter_batched(
+            || (state.clone(), data.clone()),
+            |(mut state, mut data)| {
+                write_message_in_place(&mut state, "Good morning".to_string(), 0, &mut data)
+                    .unwrap();
+                data
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_state, bench_message_write);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+//! Feeds arbitrary instruction data and account lists into `process_instruction`,
+//! failing on any panic. See `greeting_program_fuzz` for the input format.
+//!
+//! Needs a nightly toolchain and `cargo install cargo-fuzz`. From the repository root:
+//!
+//! ```text
+//! cargo run --manifest-path fuzz/Cargo.toml --bin generate_seeds
+//! cargo +nightly fuzz run process_instruction
+//! ```
+//!
+//! The first command writes one valid input per instruction into
+//! `fuzz/corpus/process_instruction`, which the fuzzer then mutates. Crashing inputs
+//! land in `fuzz/artifacts/process_instruction`; replay one with
+//! `cargo +nightly fuzz run process_instruction <file>`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    greeting_program_fuzz::run(data);
+});
@@ -0,0 +1,422 @@
+//! Writes the seed corpus for the `process_instruction` fuzz target: one valid input per
+//! instruction, with accounts laid out the way that instruction expects, so the fuzzer
+//! starts from inputs that reach the handlers.
+
+use borsh::BorshSerialize;
+use greeting_program::{
+    derive_greeting_pda, ConfigState, GreetingAccountState, GreetingInstruction, RegistryState,
+};
+use greeting_program_fuzz::{FuzzAccount, FuzzInput, AUTHORITY, FUZZ_UNIX_TIMESTAMP, PROGRAM_ID};
+use solana_program::pubkey::Pubkey;
+use std::{fs, path::Path};
+
+// Indices into the fuzz key pool, see `greeting_program_fuzz::pool_key`.
+const CONFIG: u8 = 0;
+const STATS: u8 = 1;
+const SYSTEM_PROGRAM: u8 = 2;
+const AUTHORITY_KEY: u8 = 3;
+const GREETING: u8 = 4;
+const SECOND_GREETING: u8 = 5;
+const REGISTRY: u8 = 6;
+const STRANGER: u8 = 7;
+
+// Discriminator followed by the Borsh state, zero-padded to the account's full size.
+fn tagged(discriminator: &str, state: &impl BorshSerialize, space: usize) -> Vec<u8> {
+    let mut data = discriminator.as_bytes().to_vec();
+    data.extend(borsh::to_vec(state).unwrap());
+    data.resize(space, 0);
+    data
+}
+
+fn greeting_data() -> Vec<u8> {
+    let state = GreetingAccountState {
+        version: GreetingAccountState::CURRENT_VERSION,
+        authority: AUTHORITY,
+        name: "Alice".to_string(),
+        message: "Hello".to_string(),
+        is_initialized: true,
+        is_public: true,
+        rent_payer: AUTHORITY,
+        creator: AUTHORITY,
+        ..GreetingAccountState::default()
+    };
+    tagged(
+        GreetingAccountState::ACCOUNT_DISCRIMINATOR,
+        &state,
+        GreetingAccountState::get_max_space_needed(),
+    )
+}
+
+fn registry_data() -> Vec<u8> {
+    let state = RegistryState {
+        greeting_count: 1,
+        next_index: 1,
+    };
+    tagged(
+        RegistryState::ACCOUNT_DISCRIMINATOR,
+        &state,
+        RegistryState::get_max_space_needed(),
+    )
+}
+
+fn config_data() -> Vec<u8> {
+    let state = ConfigState {
+        admin: AUTHORITY,
+        default_message: "Hello, World!".to_string(),
+        paused: false,
+        allowlist_enabled: false,
+        allowlist: vec![AUTHORITY],
+        min_update_interval_secs: 0,
+    };
+    tagged(
+        ConfigState::ACCOUNT_DISCRIMINATOR,
+        &state,
+        ConfigState::get_max_space_needed(),
+    )
+}
+
+fn account(key: u8, is_signer: bool, is_writable: bool, data: Vec<u8>) -> FuzzAccount {
+    FuzzAccount {
+        key,
+        // Accounts holding state belong to the program; empty ones are still unallocated.
+        owned_by_program: !data.is_empty(),
+        is_signer,
+        is_writable,
+        lamports: 1_000_000_000,
+        data,
+    }
+}
+
+fn readonly(key: u8) -> FuzzAccount {
+    account(key, false, false, vec![])
+}
+
+fn payer(key: u8) -> FuzzAccount {
+    account(key, true, true, vec![])
+}
+
+fn existing_greeting(key: u8) -> FuzzAccount {
+    account(key, false, true, greeting_data())
+}
+
+fn config() -> FuzzAccount {
+    account(CONFIG, false, false, config_data())
+}
+
+fn seeds() -> Vec<(GreetingInstruction, Vec<FuzzAccount>)> {
+    let name = || "Alice".to_string();
+    let message = || "Hi".to_string();
+    let create_accounts = || {
+        vec![
+            payer(AUTHORITY_KEY),
+            account(GREETING, false, true, vec![]),
+            readonly(SYSTEM_PROGRAM),
+            account(REGISTRY, false, true, vec![]),
+            config(),
+        ]
+    };
+    let edit_accounts = || {
+        vec![
+            account(AUTHORITY_KEY, true, false, vec![]),
+            existing_greeting(GREETING),
+            config(),
+        ]
+    };
+    vec![
+        (
+            GreetingInstruction::CreateGreeting {
+                name: name(),
+                message: message(),
+                index: 0,
+            },
+            create_accounts(),
+        ),
+        (
+            GreetingInstruction::SetGreeting {
+                message: message(),
+                name: None,
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::CloseGreetingAccount,
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                existing_greeting(GREETING),
+                account(STRANGER, false, true, vec![]),
+                account(REGISTRY, false, true, registry_data()),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::TransferAuthority {
+                new_authority: Pubkey::new_from_array([9; 32]),
+            },
+            edit_accounts(),
+        ),
+        (GreetingInstruction::ResetGreeting, edit_accounts()),
+        (
+            GreetingInstruction::MigrateGreeting,
+            vec![
+                payer(AUTHORITY_KEY),
+                existing_greeting(GREETING),
+                readonly(SYSTEM_PROGRAM),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::GrowGreeting { new_size: 4_096 },
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                existing_greeting(GREETING),
+                payer(STRANGER),
+                readonly(SYSTEM_PROGRAM),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::AppendToMessage {
+                suffix: "!".to_string(),
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::LikeGreeting,
+            vec![
+                account(STRANGER, true, false, vec![]),
+                existing_greeting(GREETING),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::InitializeConfig {
+                default_message: message(),
+            },
+            vec![
+                payer(AUTHORITY_KEY),
+                account(CONFIG, false, true, vec![]),
+                readonly(SYSTEM_PROGRAM),
+            ],
+        ),
+        (
+            GreetingInstruction::UpdateConfig {
+                default_message: message(),
+            },
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                account(CONFIG, false, true, config_data()),
+            ],
+        ),
+        (
+            GreetingInstruction::SetPaused { paused: true },
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                account(CONFIG, false, true, config_data()),
+            ],
+        ),
+        (
+            GreetingInstruction::RenameGreeting {
+                name: "Bob".to_string(),
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::CreateGreetingWithBump {
+                name: name(),
+                message: message(),
+                index: 0,
+                bump: derive_greeting_pda(&PROGRAM_ID, &AUTHORITY, 0).1,
+            },
+            create_accounts(),
+        ),
+        (
+            GreetingInstruction::SetVisibility { is_public: false },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::CreateNamedGreeting {
+                name: name(),
+                message: message(),
+            },
+            create_accounts(),
+        ),
+        (
+            GreetingInstruction::BatchSetGreeting {
+                messages: vec![message(), message()],
+            },
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                existing_greeting(GREETING),
+                existing_greeting(SECOND_GREETING),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::SetOrCreateGreeting {
+                name: name(),
+                message: message(),
+            },
+            create_accounts(),
+        ),
+        (
+            GreetingInstruction::LogGreetingInfo,
+            vec![existing_greeting(GREETING)],
+        ),
+        (
+            GreetingInstruction::SetCoAuthority {
+                co_authority: Some(Pubkey::new_from_array([9; 32])),
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::SetGreetingChecked {
+                message: message(),
+                expected_update_count: 0,
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::CreateGreetingWithExpiry {
+                name: name(),
+                message: message(),
+                index: 0,
+                expires_at: FUZZ_UNIX_TIMESTAMP + 60,
+            },
+            create_accounts(),
+        ),
+        (
+            GreetingInstruction::InitializeStats,
+            vec![
+                payer(AUTHORITY_KEY),
+                account(STATS, false, true, vec![]),
+                readonly(SYSTEM_PROGRAM),
+            ],
+        ),
+        (
+            GreetingInstruction::DonateToGreeting { amount: 5_000 },
+            vec![
+                payer(STRANGER),
+                existing_greeting(GREETING),
+                readonly(SYSTEM_PROGRAM),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::WithdrawDonations { amount: 5_000 },
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                existing_greeting(GREETING),
+                account(STRANGER, false, true, vec![]),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::CreateGreetingWithPdaAuthority {
+                name: name(),
+                message: message(),
+                index: 0,
+            },
+            vec![
+                payer(STRANGER),
+                // Any off-curve key passes as the authority. No pool greeting is derived
+                // from one, so this seed gets as far as the greeting PDA check.
+                account(STATS, true, false, vec![]),
+                account(GREETING, false, true, vec![]),
+                readonly(SYSTEM_PROGRAM),
+                account(REGISTRY, false, true, vec![]),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::SetGreetingIfChanged {
+                message: "Hello".to_string(),
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::RepairBump {
+                creator: AUTHORITY,
+                index: Some(0),
+                name: None,
+            },
+            edit_accounts(),
+        ),
+        (
+            GreetingInstruction::CreateCompactGreeting {
+                name: name(),
+                message: message(),
+                index: 0,
+            },
+            create_accounts(),
+        ),
+        (
+            GreetingInstruction::AddToAllowlist {
+                key: Pubkey::new_from_array([9; 32]),
+            },
+            vec![
+                payer(AUTHORITY_KEY),
+                account(CONFIG, false, true, config_data()),
+                readonly(SYSTEM_PROGRAM),
+            ],
+        ),
+        (
+            GreetingInstruction::RemoveFromAllowlist { key: AUTHORITY },
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                account(CONFIG, false, true, config_data()),
+            ],
+        ),
+        (
+            GreetingInstruction::SetAllowlistEnabled { enabled: true },
+            vec![
+                payer(AUTHORITY_KEY),
+                account(CONFIG, false, true, config_data()),
+                readonly(SYSTEM_PROGRAM),
+            ],
+        ),
+        (
+            GreetingInstruction::SetMinUpdateInterval { secs: 60 },
+            vec![
+                payer(AUTHORITY_KEY),
+                account(CONFIG, false, true, config_data()),
+                readonly(SYSTEM_PROGRAM),
+            ],
+        ),
+        (GreetingInstruction::FreezeGreeting, edit_accounts()),
+        (
+            GreetingInstruction::CloseGreetingToRentPayer,
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                existing_greeting(GREETING),
+                account(AUTHORITY_KEY, false, true, vec![]),
+                account(REGISTRY, false, true, registry_data()),
+                config(),
+            ],
+        ),
+        (
+            GreetingInstruction::SweepSurplus,
+            vec![
+                account(AUTHORITY_KEY, true, false, vec![]),
+                existing_greeting(GREETING),
+                account(STRANGER, false, true, vec![]),
+                config(),
+            ],
+        ),
+    ]
+}
+
+fn main() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus/process_instruction");
+    fs::create_dir_all(&corpus).unwrap();
+    for (instruction, accounts) in seeds() {
+        let instruction_data = instruction.pack();
+        let input = FuzzInput {
+            accounts,
+            instruction_data,
+        };
+        // Named after the instruction's wire index, so reruns overwrite rather than pile up
+        let path = corpus.join(format!("seed-{:02}", input.instruction_data[0]));
+        fs::write(&path, borsh::to_vec(&input).unwrap()).unwrap();
+        println!("wrote {}", path.display());
+    }
+}
@@ -0,0 +1,178 @@
+//! Input format and harness shared by the `process_instruction` fuzz target and the
+//! `generate_seeds` binary, so seeds are encoded exactly the way the target decodes them.
+//!
+//! The fuzzer's bytes are read as a Borsh-encoded `FuzzInput`. Account keys come from a
+//! small pool holding the program's real PDAs, so mutated inputs regularly get past the
+//! PDA checks and into the handlers instead of all failing on the first mismatch.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use greeting_program::{
+    derive_config_pda, derive_greeting_pda, derive_registry_pda, derive_stats_pda,
+    process_instruction, GreetingInstruction,
+};
+use solana_program::{
+    clock::Clock,
+    entrypoint::{self, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER, SUCCESS},
+    program_stubs,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use std::sync::Once;
+
+/// Program id the fuzzed instructions run under.
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([1; 32]);
+
+/// The one authority in the key pool; its greetings and registry are in the pool too.
+pub const AUTHORITY: Pubkey = Pubkey::new_from_array([2; 32]);
+
+/// Any other key, for wrong signers, destinations and foreign owners.
+pub const STRANGER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+/// Unix time the stubbed Clock reports.
+pub const FUZZ_UNIX_TIMESTAMP: i64 = 1_700_000_000;
+
+// More accounts than any instruction takes; longer lists just slow the fuzzer down.
+const MAX_ACCOUNTS: usize = 32;
+
+/// One account passed to `process_instruction`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct FuzzAccount {
+    /// Index into the key pool, see `pool_key`.
+    pub key: u8,
+    /// Owned by the program if set, otherwise by the System Program.
+    pub owned_by_program: bool,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+}
+
+/// Everything one fuzz run feeds to `process_instruction`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct FuzzInput {
+    pub accounts: Vec<FuzzAccount>,
+    pub instruction_data: Vec<u8>,
+}
+
+/// The key pool entry `index` picks, wrapping around.
+pub fn pool_key(index: u8) -> Pubkey {
+    match index % 8 {
+        0 => derive_config_pda(&PROGRAM_ID).0,
+        1 => derive_stats_pda(&PROGRAM_ID).0,
+        2 => system_program::ID,
+        3 => AUTHORITY,
+        4 => derive_greeting_pda(&PROGRAM_ID, &AUTHORITY, 0).0,
+        5 => derive_greeting_pda(&PROGRAM_ID, &AUTHORITY, 1).0,
+        6 => derive_registry_pda(&PROGRAM_ID, &AUTHORITY).0,
+        _ => STRANGER,
+    }
+}
+
+// Serves the Clock and Rent sysvars so handlers get past reading them, and drops event
+// data, which nobody reads here.
+struct FuzzSyscallStubs;
+
+impl program_stubs::SyscallStubs for FuzzSyscallStubs {
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: FUZZ_UNIX_TIMESTAMP,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+}
+
+/// Runs one fuzz input. Panics only on a bug: `process_instruction` must return an error
+/// for bad input, never unwind.
+pub fn run(data: &[u8]) {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(FuzzSyscallStubs));
+    });
+
+    let Ok(input) = FuzzInput::try_from_slice(data) else {
+        return;
+    };
+    if input.accounts.len() > MAX_ACCOUNTS {
+        return;
+    }
+
+    // Borsh decoding is canonical, so anything `unpack` accepts must encode back to the
+    // very same bytes. This checks the `try_from_slice` edge cases (bad bool and Option
+    // tags, invalid UTF-8, truncated lengths) directly, not only through the handlers.
+    // The one exception is SetGreeting in its encoding from before `name`, which packs
+    // with the `None` tag appended.
+    if let Ok(instruction) = GreetingInstruction::unpack(&input.instruction_data) {
+        let packed = instruction.pack();
+        if packed != input.instruction_data {
+            assert!(matches!(
+                instruction,
+                GreetingInstruction::SetGreeting { name: None, .. }
+            ));
+            assert_eq!(packed, [&input.instruction_data[..], &[0]].concat());
+        }
+    }
+
+    // Back the input with u64s so it has the 8-byte alignment the runtime guarantees.
+    let bytes = serialize_input(&input);
+    let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+    let buffer_ptr = buffer.as_mut_ptr() as *mut u8;
+    // SAFETY: `buffer` holds `bytes`, laid out as the runtime lays out program input, and
+    // outlives the account infos borrowed from it.
+    let (program_id, account_infos, instruction_data) = unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer_ptr, bytes.len());
+        entrypoint::deserialize(buffer_ptr)
+    };
+    let _ = process_instruction(program_id, &account_infos, instruction_data);
+}
+
+// Encodes the input exactly as the runtime serializes a program's input, including the
+// spare room after each account's data, so `entrypoint::deserialize` hands back account
+// infos that `realloc` works on. A repeated key is passed as a duplicate of its first
+// occurrence, sharing its data and lamports, again as the runtime does.
+fn serialize_input(input: &FuzzInput) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(input.accounts.len() as u64).to_le_bytes());
+    let mut keys: Vec<Pubkey> = Vec::new();
+    for account in &input.accounts {
+        let key = pool_key(account.key);
+        if let Some(first) = keys.iter().position(|seen| *seen == key) {
+            bytes.push(first as u8);
+            bytes.extend_from_slice(&[0; 7]);
+            keys.push(key);
+            continue;
+        }
+        keys.push(key);
+        let owner = if account.owned_by_program {
+            PROGRAM_ID
+        } else {
+            system_program::ID
+        };
+        bytes.push(NON_DUP_MARKER);
+        bytes.push(account.is_signer.into());
+        bytes.push(account.is_writable.into());
+        bytes.push(0); // executable
+        bytes.extend_from_slice(&[0; 4]); // original data length, filled in by deserialize
+        bytes.extend_from_slice(key.as_ref());
+        bytes.extend_from_slice(owner.as_ref());
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+    }
+    bytes.extend_from_slice(&(input.instruction_data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&input.instruction_data);
+    bytes.extend_from_slice(PROGRAM_ID.as_ref());
+    bytes
+}
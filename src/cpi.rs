@@ -0,0 +1,156 @@
+//! Helpers for other programs that call into this one.
+//!
+//! Unlike the `client` builders, these take the `AccountInfo`s the calling program
+//! already holds, so no PDA derivation happens here: the caller passes the accounts
+//! through from its own instruction, in the order the program expects.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+    program::invoke_signed,
+};
+
+use crate::instruction::{
+    accounts::{CreateGreetingAccounts, CreateGreetingWithPdaAuthorityAccounts},
+    GreetingInstruction,
+};
+
+/// Accounts for a `CreateGreeting` CPI. Every one must also be passed to the calling
+/// program, since a CPI can only use accounts the outer transaction already lists.
+pub struct CreateGreetingCpiAccounts<'a, 'info> {
+    /// This program's executable account; its key is the program id invoked.
+    pub greeting_program: &'a AccountInfo<'info>,
+    /// Pays rent and becomes the greeting's authority. Must be writable, and either
+    /// sign the outer transaction or be a PDA of the calling program.
+    pub payer: &'a AccountInfo<'info>,
+    /// `derive_greeting_pda(greeting_program.key, payer.key, index)`; writable.
+    pub greeting: &'a AccountInfo<'info>,
+    /// The System Program.
+    pub system_program: &'a AccountInfo<'info>,
+    /// `derive_registry_pda(greeting_program.key, payer.key)`; writable.
+    pub registry: &'a AccountInfo<'info>,
+    /// `derive_config_pda(greeting_program.key)`; may be uninitialized.
+    pub config: &'a AccountInfo<'info>,
+}
+
+/// Invokes `CreateGreeting` with the given accounts.
+///
+/// `signer_seeds` are forwarded to `invoke_signed`. Pass `&[]` when `payer` signed the
+/// outer transaction, since its signature carries through the CPI. When `payer` is a PDA
+/// of the calling program, pass that PDA's seeds (including its bump) so the runtime
+/// treats it as a signer; the System Program then sees it as signing too, which lets it
+/// fund the new accounts. The greeting's own PDA is signed for by this program.
+pub fn create_greeting(
+    accounts: CreateGreetingCpiAccounts,
+    name: String,
+    message: String,
+    index: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let data = GreetingInstruction::CreateGreeting {
+        name,
+        message,
+        index,
+    }
+    .pack();
+    let instruction = Instruction::new_with_bytes(
+        *accounts.greeting_program.key,
+        &data,
+        CreateGreetingAccounts {
+            payer: *accounts.payer.key,
+            greeting_pda: *accounts.greeting.key,
+            system_program: *accounts.system_program.key,
+            registry: *accounts.registry.key,
+            co_authority: None,
+            config: *accounts.config.key,
+        }
+        .to_account_metas(),
+    );
+    invoke_signed(
+        &instruction,
+        &[
+            accounts.payer.clone(),
+            accounts.greeting.clone(),
+            accounts.system_program.clone(),
+            accounts.registry.clone(),
+            accounts.config.clone(),
+            accounts.greeting_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// Accounts for a `CreateGreetingWithPdaAuthority` CPI. As for `CreateGreetingCpiAccounts`,
+/// every one must also be passed to the calling program.
+pub struct CreateGreetingWithPdaAuthorityCpiAccounts<'a, 'info> {
+    /// This program's executable account; its key is the program id invoked.
+    pub greeting_program: &'a AccountInfo<'info>,
+    /// Pays rent. Must be writable and sign, usually by signing the outer transaction.
+    pub payer: &'a AccountInfo<'info>,
+    /// A PDA of the calling program, which becomes the greeting's authority.
+    pub authority: &'a AccountInfo<'info>,
+    /// `derive_greeting_pda(greeting_program.key, authority.key, index)`; writable.
+    pub greeting: &'a AccountInfo<'info>,
+    /// The System Program.
+    pub system_program: &'a AccountInfo<'info>,
+    /// `derive_registry_pda(greeting_program.key, authority.key)`; writable.
+    pub registry: &'a AccountInfo<'info>,
+    /// `derive_config_pda(greeting_program.key)`; may be uninitialized.
+    pub config: &'a AccountInfo<'info>,
+}
+
+/// Invokes `CreateGreetingWithPdaAuthority`, creating a greeting that only the calling
+/// program controls.
+///
+/// `signer_seeds` must hold the seeds of `authority` (including its bump) under the
+/// calling program's id, which is what makes it a signer of the CPI; add the payer's
+/// seeds too if it is also a PDA. Every later change to the greeting works the same way:
+/// build the instruction with `authority` as the signing authority and pass the same
+/// seeds to `invoke_signed`, e.g. for `SetGreeting`:
+///
+/// ```ignore
+/// invoke_signed(
+///     &instruction::set_greeting(greeting_program.key, authority.key, greeting.key, message),
+///     &[authority.clone(), greeting.clone(), config.clone(), greeting_program.clone()],
+///     &[&[AUTHORITY_SEED, &[authority_bump]]],
+/// )?;
+/// ```
+pub fn create_greeting_with_pda_authority(
+    accounts: CreateGreetingWithPdaAuthorityCpiAccounts,
+    name: String,
+    message: String,
+    index: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let data = GreetingInstruction::CreateGreetingWithPdaAuthority {
+        name,
+        message,
+        index,
+    }
+    .pack();
+    let instruction = Instruction::new_with_bytes(
+        *accounts.greeting_program.key,
+        &data,
+        CreateGreetingWithPdaAuthorityAccounts {
+            payer: *accounts.payer.key,
+            authority: *accounts.authority.key,
+            greeting_pda: *accounts.greeting.key,
+            system_program: *accounts.system_program.key,
+            registry: *accounts.registry.key,
+            config: *accounts.config.key,
+        }
+        .to_account_metas(),
+    );
+    invoke_signed(
+        &instruction,
+        &[
+            accounts.payer.clone(),
+            accounts.authority.clone(),
+            accounts.greeting.clone(),
+            accounts.system_program.clone(),
+            accounts.registry.clone(),
+            accounts.config.clone(),
+            accounts.greeting_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
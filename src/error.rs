@@ -0,0 +1,247 @@
+use solana_program::{msg, program_error::ProgramError};
+use thiserror::Error;
+
+/// Errors specific to the greeting program.
+///
+/// Each variant is returned to clients as `ProgramError::Custom(code)`. The codes
+/// are part of the program's public interface, so never renumber existing variants;
+/// only append new ones.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GreetingError {
+    /// The name is longer than `MAX_NAME_LENGTH` bytes.
+    #[error("Name exceeds the maximum length")]
+    NameTooLong = 0,
+    /// The message is longer than `MAX_MESSAGE_CHARS` characters (grapheme clusters with
+    /// the `graphemes` feature) or `MAX_MESSAGE_LENGTH` bytes.
+    #[error("Message exceeds the maximum length")]
+    MessageTooLong = 1,
+    /// The signer is not the greeting's stored authority.
+    #[error("Signer is not the greeting authority")]
+    InvalidAuthority = 2,
+    /// The greeting account holds no valid greeting state.
+    #[error("Greeting account is not initialized")]
+    AccountNotInitialized = 3,
+    /// The greeting account passed doesn't match the derived PDA.
+    #[error("Greeting account does not match the derived PDA")]
+    PdaMismatch = 4,
+    /// The greeting account already holds data.
+    #[error("Greeting account is already initialized")]
+    AccountAlreadyInitialized = 5,
+    /// The requested authority can never sign: the default Pubkey, which is also the
+    /// System Program's ID, or the program's own ID.
+    #[error("New authority is not a usable key")]
+    InvalidNewAuthority = 6,
+    /// The Clock sysvar could not be read.
+    #[error("Clock sysvar is unavailable")]
+    ClockUnavailable = 7,
+    /// A required name or message is empty.
+    #[error("Name and message must not be empty")]
+    EmptyField = 8,
+    /// The text contains a disallowed character, such as a null byte or another ASCII
+    /// control character.
+    #[error("Text contains an invalid character")]
+    InvalidCharacter = 9,
+    /// The greeting account is tagged with a layout version this build doesn't know.
+    #[error("Greeting account version is not supported")]
+    UnsupportedAccountVersion = 10,
+    /// The signer is not the config admin.
+    #[error("Signer is not the config admin")]
+    InvalidAdmin = 11,
+    /// The admin has paused the program.
+    #[error("Program is paused")]
+    ProgramPaused = 12,
+    /// The authority already holds `MAX_GREETINGS_PER_AUTHORITY` greetings.
+    #[error("Authority has too many greetings")]
+    TooManyGreetings = 13,
+    /// The greeting is private and can't be liked.
+    #[error("Greeting is private")]
+    GreetingPrivate = 14,
+    /// The greeting was edited since the client read it: `update_count` doesn't match.
+    #[error("Greeting has changed since it was read")]
+    StaleUpdate = 15,
+    /// The greeting is past its `expires_at` and can no longer be edited, only closed.
+    #[error("Greeting has expired")]
+    Expired = 16,
+    /// With the `strict` feature: an account is signer or writable where the instruction
+    /// doesn't list that flag.
+    #[error("Account carries a flag the instruction does not expect")]
+    UnexpectedAccountFlags = 17,
+    /// The withdrawal would leave the greeting account below its rent-exempt minimum.
+    #[error("Withdrawal would leave the account below rent exemption")]
+    WouldBreakRentExemption = 18,
+    /// An account the instruction writes to was passed read-only.
+    #[error("Account must be writable")]
+    AccountNotWritable = 19,
+    /// The same account was passed for two roles that must be different accounts, such as
+    /// the payer and the greeting it creates.
+    #[error("Account passed for two roles that must differ")]
+    DuplicateAccount = 20,
+    /// The config's allowlist gate is on and the new greeting's authority isn't on it.
+    #[error("Authority is not on the allowlist")]
+    NotAllowlisted = 21,
+    /// The allowlist already holds `MAX_ALLOWLIST_LEN` keys.
+    #[error("Allowlist is full")]
+    AllowlistFull = 22,
+    /// The greeting was edited less than the config's `min_update_interval_secs` ago.
+    #[error("Greeting was updated too recently")]
+    TooSoon = 23,
+    /// The greeting has been frozen by FreezeGreeting and can no longer change.
+    #[error("Greeting is frozen")]
+    GreetingFrozen = 24,
+    /// An account the instruction writes to is an executable program account.
+    #[error("Account is executable")]
+    ExecutableAccount = 25,
+}
+
+impl GreetingError {
+    /// The variant's message as a static string, for `msg!` without the allocation
+    /// `Display` and `format!` would cost. Matches the `#[error]` messages.
+    pub fn as_str(&self) -> &'static str {
+        use GreetingError::*;
+        match self {
+            NameTooLong => "Name exceeds the maximum length",
+            MessageTooLong => "Message exceeds the maximum length",
+            InvalidAuthority => "Signer is not the greeting authority",
+            AccountNotInitialized => "Greeting account is not initialized",
+            PdaMismatch => "Greeting account does not match the derived PDA",
+            AccountAlreadyInitialized => "Greeting account is already initialized",
+            InvalidNewAuthority => "New authority is not a usable key",
+            ClockUnavailable => "Clock sysvar is unavailable",
+            EmptyField => "Name and message must not be empty",
+            InvalidCharacter => "Text contains an invalid character",
+            UnsupportedAccountVersion => "Greeting account version is not supported",
+            InvalidAdmin => "Signer is not the config admin",
+            ProgramPaused => "Program is paused",
+            TooManyGreetings => "Authority has too many greetings",
+            GreetingPrivate => "Greeting is private",
+            StaleUpdate => "Greeting has changed since it was read",
+            Expired => "Greeting has expired",
+            UnexpectedAccountFlags => "Account carries a flag the instruction does not expect",
+            WouldBreakRentExemption => "Withdrawal would leave the account below rent exemption",
+            AccountNotWritable => "Account must be writable",
+            DuplicateAccount => "Account passed for two roles that must differ",
+            NotAllowlisted => "Authority is not on the allowlist",
+            AllowlistFull => "Allowlist is full",
+            TooSoon => "Greeting was updated too recently",
+            GreetingFrozen => "Greeting is frozen",
+            ExecutableAccount => "Account is executable",
+        }
+    }
+}
+
+impl From<GreetingError> for ProgramError {
+    fn from(e: GreetingError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl TryFrom<u32> for GreetingError {
+    type Error = ProgramError;
+
+    /// Recovers the variant from a `ProgramError::Custom` code.
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        use GreetingError::*;
+        Ok(match code {
+            0 => NameTooLong,
+            1 => MessageTooLong,
+            2 => InvalidAuthority,
+            3 => AccountNotInitialized,
+            4 => PdaMismatch,
+            5 => AccountAlreadyInitialized,
+            6 => InvalidNewAuthority,
+            7 => ClockUnavailable,
+            8 => EmptyField,
+            9 => InvalidCharacter,
+            10 => UnsupportedAccountVersion,
+            11 => InvalidAdmin,
+            12 => ProgramPaused,
+            13 => TooManyGreetings,
+            14 => GreetingPrivate,
+            15 => StaleUpdate,
+            16 => Expired,
+            17 => UnexpectedAccountFlags,
+            18 => WouldBreakRentExemption,
+            19 => AccountNotWritable,
+            20 => DuplicateAccount,
+            21 => NotAllowlisted,
+            22 => AllowlistFull,
+            23 => TooSoon,
+            24 => GreetingFrozen,
+            25 => ExecutableAccount,
+            _ => return Err(ProgramError::Custom(code)),
+        })
+    }
+}
+
+/// Logs an error returned by `process_instruction` with its numeric code, naming the
+/// variant and message for `GreetingError` codes, in the spirit of SPL's
+/// `PrintProgramError`. The runtime only logs the code, and `Custom(n)` means nothing
+/// without this crate's source at hand.
+pub fn print_program_error(error: &ProgramError) {
+    msg!("{}", error_log_line(error));
+}
+
+// The line `print_program_error` logs. Off-chain `msg!` goes straight to stdout, so tests
+// check this instead.
+fn error_log_line(error: &ProgramError) -> String {
+    match error {
+        ProgramError::Custom(code) => match GreetingError::try_from(*code) {
+            Ok(greeting_error) => format!(
+                "Error {}: GreetingError::{:?}: {}",
+                code,
+                greeting_error,
+                greeting_error.as_str()
+            ),
+            Err(_) => format!("Error {}: unknown custom error", code),
+        },
+        // Builtin errors are reported to clients by their u64 encoding
+        error => format!("Error {}: {}", u64::from(error.clone()), error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_round_trip() {
+        for code in 0..=GreetingError::ExecutableAccount as u32 {
+            let error = GreetingError::try_from(code).unwrap();
+            assert_eq!(error as u32, code);
+            assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+        }
+        let unknown = GreetingError::ExecutableAccount as u32 + 1;
+        assert_eq!(
+            GreetingError::try_from(unknown),
+            Err(ProgramError::Custom(unknown))
+        );
+    }
+
+    #[test]
+    fn test_as_str_is_unique_and_matches_display() {
+        let mut seen = std::collections::HashSet::new();
+        for code in 0..=GreetingError::ExecutableAccount as u32 {
+            let error = GreetingError::try_from(code).unwrap();
+            assert!(!error.as_str().is_empty(), "{:?}", error);
+            assert!(seen.insert(error.as_str()), "{:?} repeats a message", error);
+            assert_eq!(error.as_str(), error.to_string());
+        }
+    }
+
+    #[test]
+    fn test_error_log_line_names_greeting_errors() {
+        assert_eq!(
+            error_log_line(&GreetingError::InvalidAuthority.into()),
+            "Error 2: GreetingError::InvalidAuthority: Signer is not the greeting authority"
+        );
+        assert_eq!(
+            error_log_line(&ProgramError::Custom(999)),
+            "Error 999: unknown custom error"
+        );
+        let builtin = ProgramError::InvalidInstructionData;
+        assert_eq!(
+            error_log_line(&builtin),
+            format!("Error {}: {}", u64::from(builtin.clone()), builtin)
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Structured events for off-chain indexers.
+//!
+//! After every successful mutation the program emits one `GreetingEvent` through
+//! `sol_log_data`, so it shows up in the transaction logs as a single
+//! `Program data: <base64>` line. Decoding that base64 payload with
+//! `GreetingEvent::try_from_slice` yields the event. The payload is plain Borsh: a
+//! one-byte variant index followed by the variant's fields in declaration order, with
+//! each `Pubkey` as 32 raw bytes, integers little-endian and strings as a `u32` length
+//! then UTF-8. New variants and fields are only ever appended. The variant indices are:
+//!
+//! - `Created` = 0
+//! - `Updated` = 1
+//! - `Closed` = 2
+//! - `Liked` = 3
+//! - `NamedCreated` = 4
+//! - `Donated` = 5
+//! - `Withdrawn` = 6
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum GreetingEvent {
+    /// A greeting account was created.
+    Created {
+        greeting: Pubkey,
+        authority: Pubkey,
+        index: u64,
+    },
+    /// An existing greeting account's state changed.
+    Updated {
+        greeting: Pubkey,
+        authority: Pubkey,
+        update_count: u32,
+    },
+    /// A greeting account was closed and its lamports sent to `destination`.
+    Closed {
+        greeting: Pubkey,
+        destination: Pubkey,
+        lamports: u64,
+    },
+    /// A greeting received a like from `liker`.
+    Liked {
+        greeting: Pubkey,
+        liker: Pubkey,
+        likes: u32,
+    },
+    /// A greeting account was created at its name-derived address.
+    NamedCreated {
+        greeting: Pubkey,
+        authority: Pubkey,
+        name: String,
+    },
+    /// `donor` sent `amount` lamports to a greeting, bringing its `total_donations` to
+    /// `total`.
+    Donated {
+        greeting: Pubkey,
+        donor: Pubkey,
+        amount: u64,
+        total: u64,
+    },
+    /// The authority withdrew `amount` lamports from a greeting to `destination`.
+    Withdrawn {
+        greeting: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+    },
+}
+
+impl GreetingEvent {
+    /// Writes the Borsh-encoded event to the transaction logs.
+    pub fn emit(&self) {
+        // Serializing into a `Vec` can't fail, and a missing event must never fail the
+        // instruction.
+        if let Ok(data) = borsh::to_vec(self) {
+            sol_log_data(&[&data]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_layout() {
+        let greeting = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let event = GreetingEvent::Closed {
+            greeting,
+            destination,
+            lamports: 42,
+        };
+
+        let data = borsh::to_vec(&event).unwrap();
+        assert_eq!(data[0], 2);
+        assert_eq!(&data[1..33], greeting.as_ref());
+        assert_eq!(&data[33..65], destination.as_ref());
+        assert_eq!(&data[65..], &42u64.to_le_bytes());
+        assert_eq!(GreetingEvent::try_from_slice(&data).unwrap(), event);
+    }
+}
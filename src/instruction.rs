@@ -0,0 +1,1888 @@
+//! The instructions understood by the program, the typed account lists in `accounts`
+//! and, with the `client` feature, builders that assemble a complete `Instruction` for
+//! each variant with the account metas in the order the program expects, taken from
+//! those lists.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+pub mod accounts;
+
+#[cfg(feature = "client")]
+pub use self::builders::*;
+
+/// Instructions understood by the program.
+///
+/// Every instruction that changes a greeting also takes the config PDA,
+/// `derive_config_pda(program_id)`, as its last account, after the ones listed
+/// below. It may be uninitialized; if it holds a config with `paused` set, the
+/// instruction fails with `GreetingError::ProgramPaused`.
+///
+/// With the `strict` feature, an account may also carry only the flags listed for
+/// it, or the instruction fails with `GreetingError::UnexpectedAccountFlags`: a `[]`
+/// account must neither sign nor be writable, a `[writable]` one must not sign, and a
+/// `[signer]` one must not be writable. The trailing config PDA may be writable, since
+/// flags are per transaction and another instruction may be changing the config. Optional
+/// accounts (a co-authority, the stats PDA) must match the flags listed for one of
+/// them, and any accounts past the documented ones must be `[]`. Two exceptions keep
+/// ordinary transactions working:
+/// - a signer may always be writable, since the fee payer always is;
+/// - `CloseGreetingAccount`'s destination may sign, since it is usually the fee payer.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum GreetingInstruction {
+    /// Creates a new greeting account (PDA) and sets an initial message.
+    ///
+    /// Accounts expected by this instruction:
+    /// 0. `[signer, writable]` `payer_account`: The account paying for the new greeting account's rent
+    ///    and whose key will be used as a seed for the PDA. Becomes the authority.
+    /// 1. `[writable]` `greeting_account_pda`: The PDA to be created and initialized.
+    ///    Its address is `derive_greeting_pda(program_id, payer_account.key, index)`; the `name`
+    ///    is not a seed. The client must pass the correct derived address here.
+    /// 2. `[]` `system_program`: The Solana System Program, required for creating accounts.
+    /// 3. `[writable]` `registry_account`: The payer's registry PDA,
+    ///    `derive_registry_pda(program_id, payer_account.key)`. Created on first use; creation
+    ///    fails with `TooManyGreetings` once it counts `MAX_GREETINGS_PER_AUTHORITY`.
+    /// 4. `[writable]` Optional: the stats PDA, `derive_stats_pda(program_id)`. When passed
+    ///    and initialized (see `InitializeStats`), its `total_greetings` goes up by one.
+    ///    Leave it out and the greeting is created without being counted.
+    ///
+    /// `message` may be a template using `{name}`, stored as written; clients show it
+    /// through `GreetingAccountState::rendered_message`.
+    ///
+    /// `index` lets one payer own many greetings. It is deliberately the last field:
+    /// payloads from clients predating it are too short to decode and fail with
+    /// `InvalidInstructionData` instead of creating an account at the wrong address.
+    CreateGreeting {
+        name: String,
+        message: String,
+        index: u64,
+    },
+
+    /// Sets a new greeting message on an existing greeting account. The message it replaces
+    /// goes into the greeting's history, which keeps the last `HISTORY_LEN` of them.
+    /// The account is always written, even when `message` equals the current one, so every
+    /// call counts towards `update_count`; use `SetGreetingIfChanged` to skip those.
+    ///
+    /// `name`, if set, renames the greeting in the same edit, as `RenameGreeting` would,
+    /// and is checked against `MAX_NAME_LENGTH` like the message is against its limit. It was
+    /// added after deployed clients started sending `SetGreeting`, so the program also
+    /// accepts the older encoding, the message alone, as `name: None`; see `unpack`.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) whose message is to be changed.
+    /// 2. `[signer]` The co-authority, only if the greeting has one (see `SetCoAuthority`).
+    /// 3. `[writable]` Optional: the stats PDA, whose `total_updates` goes up by one, as
+    ///    for `CreateGreeting`. Comes straight after the greeting if there's no co-authority.
+    SetGreeting {
+        message: String,
+        name: Option<String>,
+    },
+
+    /// Closes a greeting account and reclaims its rent lamports.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account. Once the greeting has expired this
+    ///    can be anyone, and needn't sign.
+    /// 1. `[writable]` The greeting account (PDA) to close.
+    /// 2. `[writable]` The account that receives the reclaimed lamports. For an expired
+    ///    greeting closed by someone else, this must be the greeting's authority.
+    /// 3. `[writable]` The registry PDA of the greeting's `creator`, the authority it was created
+    ///    under. Its count goes down by one if it exists. A greeting received through
+    ///    TransferAuthority frees the slot of its creator, who was charged for it, not of its
+    ///    new authority. Greetings from before `creator` was recorded use the registry of
+    ///    whoever is authority at close time.
+    /// 4. `[signer]` The co-authority, only if the greeting has one and hasn't expired.
+    CloseGreetingAccount,
+
+    /// Hands control of a greeting account to a new authority. Fails with
+    /// `InvalidNewAuthority` if the new authority is the greeting's co-authority, which
+    /// stays as it is.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The current authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) whose authority is to be changed.
+    /// 2. `[signer]` The co-authority, only if the greeting has one.
+    TransferAuthority { new_authority: Pubkey },
+
+    /// Sets the message back to the default, keeping the name. The default comes from the
+    /// config account if it is initialized, otherwise it's `GreetingAccountState::DEFAULT_MESSAGE`.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to reset.
+    /// 2. `[]` The config PDA.
+    ///
+    /// Like `SetGreeting`, a greeting with a co-authority also needs it to sign, passed
+    /// before the config PDA.
+    ResetGreeting,
+
+    /// Upgrades a greeting account to `GreetingAccountState::CURRENT_VERSION`, growing it if
+    /// needed. Does nothing if the account is already current.
+    ///
+    /// Optional: older layouts are read as they are and upgraded by the next write, as
+    /// long as the current layout fits the account. Migrate an account that's too small
+    /// for its next edit, or to pay for the full reservation up front.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The authority of the greeting account, which funds any extra rent.
+    /// 1. `[writable]` The greeting account (PDA) to migrate.
+    /// 2. `[]` The System Program, used to transfer the extra rent.
+    MigrateGreeting,
+
+    /// Grows a greeting account to `new_size` bytes, e.g. after an upgrade raised
+    /// `MAX_MESSAGE_LENGTH`. Accounts can't shrink, and can grow by at most
+    /// `MAX_PERMITTED_DATA_INCREASE` (10 KiB) per instruction.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to grow.
+    /// 2. `[signer, writable]` The account paying the extra rent.
+    /// 3. `[]` The System Program, used to transfer the extra rent.
+    GrowGreeting { new_size: u64 },
+
+    /// Appends `suffix` to the current message, as long as the result fits in
+    /// `MAX_MESSAGE_CHARS`.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) whose message is extended.
+    /// 2. `[signer]` The co-authority, only if the greeting has one (see `SetCoAuthority`).
+    AppendToMessage { suffix: String },
+
+    /// Adds one like to a greeting. Anyone may call this, not just the authority.
+    ///
+    /// Likes are not tracked per liker: the same wallet can like a greeting any
+    /// number of times and every call counts. Requiring a signer only means each
+    /// like costs its sender a transaction fee.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The account liking the greeting.
+    /// 1. `[writable]` The greeting account (PDA) being liked.
+    LikeGreeting,
+
+    /// Creates the program's config account and makes the payer its admin.
+    /// Whoever calls this first becomes admin, so deployers should run it right
+    /// after deploying.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The account paying for the config account. Becomes the admin.
+    /// 1. `[writable]` The config PDA, `derive_config_pda(program_id)`.
+    /// 2. `[]` The System Program, required for creating accounts.
+    InitializeConfig { default_message: String },
+
+    /// Changes the default message stored in the config account.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The config admin.
+    /// 1. `[writable]` The config PDA.
+    UpdateConfig { default_message: String },
+
+    /// Pauses or unpauses every instruction that changes a greeting. Config
+    /// instructions keep working so the admin can always unpause.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The config admin.
+    /// 1. `[writable]` The config PDA.
+    SetPaused { paused: bool },
+
+    /// Changes the greeting's name, keeping its message. Counts as an update.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to rename.
+    /// 2. `[signer]` The co-authority, only if the greeting has one (see `SetCoAuthority`).
+    RenameGreeting { name: String },
+
+    /// Same as `CreateGreeting`, but the client supplies the PDA's bump.
+    ///
+    /// `CreateGreeting` derives the address with `find_program_address`, which tries bumps
+    /// from 255 downwards and pays about 1,500 compute units for each one. Here the program
+    /// makes a single `create_program_address` call with `bump` and checks the result against
+    /// the passed account, so a client that has already derived the PDA saves the search.
+    /// A bump that doesn't produce the passed address fails with `PdaMismatch`.
+    ///
+    /// The bump is not checked to be the canonical one, since that would cost the same search.
+    /// Clients should pass the bump from `derive_greeting_pda`; any other valid bump creates the
+    /// greeting at an address the standard derivation won't find.
+    ///
+    /// Accounts expected: the same as `CreateGreeting`.
+    CreateGreetingWithBump {
+        name: String,
+        message: String,
+        index: u64,
+        bump: u8,
+    },
+
+    /// Makes the greeting public or private. Private greetings reject `LikeGreeting`;
+    /// off-chain tooling may also hide them.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to change.
+    /// 2. `[signer]` The co-authority, only if the greeting has one.
+    SetVisibility { is_public: bool },
+
+    /// Creates a greeting at `derive_named_greeting_pda(program_id, payer, name)`
+    /// instead of an indexed address, so each wallet has at most one greeting per name.
+    ///
+    /// Accounts expected: the same as `CreateGreeting`, with the named PDA at index 1.
+    CreateNamedGreeting { name: String, message: String },
+
+    /// Sets a new message on several greetings with the same authority at once.
+    /// `messages[i]` goes to the greeting at account `i + 1`. Every message and
+    /// every greeting is checked before any is written, so one bad entry fails
+    /// the whole instruction and nothing changes. A greeting may appear only once.
+    ///
+    /// Accounts expected by this instruction:
+    /// 0. `[signer]` The authority of every greeting account.
+    /// 1..=N. `[writable]` The greeting accounts (PDAs) to update, one per message.
+    /// N+1.. `[signer]` The co-authority of each greeting that has one, in the greetings'
+    ///    order (see `SetCoAuthority`).
+    ///
+    /// The practical limit is the 1232-byte transaction size rather than the account
+    /// count: each entry costs a 32-byte key plus its message, so a legacy transaction
+    /// fits about 20 short messages, or 6 of `MAX_MESSAGE_CHARS` ASCII characters.
+    BatchSetGreeting { messages: Vec<String> },
+
+    /// Upserts the named greeting: creates it like `CreateNamedGreeting` if its account
+    /// doesn't exist yet, otherwise sets `message` on it like `SetGreeting`, so clients
+    /// needn't check for the account first. When updating, the signer must be the
+    /// authority and `name` must be the one the address was derived from; the stored
+    /// name is left as it is.
+    ///
+    /// Accounts expected: the same as `CreateNamedGreeting`. When updating a greeting that
+    /// has a co-authority, it follows the registry and signs (see `SetCoAuthority`), ahead
+    /// of the optional stats PDA.
+    SetOrCreateGreeting { name: String, message: String },
+
+    /// Diagnostic only: logs the greeting's state along with the account's lamports, owner,
+    /// data length and rent epoch. Changes nothing, needs no signer and works while paused.
+    ///
+    /// Accounts expected:
+    /// 0. `[]` The greeting account to inspect.
+    LogGreetingInfo,
+
+    /// Sets or clears the greeting's co-authority. While one is set, every instruction that
+    /// writes the greeting's message or name needs both the authority and the co-authority to
+    /// sign, and so do `SetVisibility`, `FreezeGreeting`, `TransferAuthority` and closing it
+    /// before it expires. Replacing or clearing an existing co-authority
+    /// needs its signature too, so the authority can't drop it alone.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to change.
+    /// 2. `[signer]` The current co-authority, only if the greeting has one.
+    SetCoAuthority { co_authority: Option<Pubkey> },
+
+    /// `SetGreeting` with optimistic concurrency: fails with `StaleUpdate` unless the
+    /// greeting's `update_count` still equals `expected_update_count`, so a queued update
+    /// signed against an older version can't overwrite a newer message. Once
+    /// `update_count` saturates at `u32::MAX` it no longer changes, and neither does
+    /// this check.
+    ///
+    /// Accounts expected: the same as `SetGreeting`.
+    SetGreetingChecked {
+        message: String,
+        expected_update_count: u32,
+    },
+
+    /// `CreateGreeting` for an ephemeral greeting that expires at unix time `expires_at`,
+    /// which must be in the future. From then on its message can't be changed and anyone
+    /// may close it with `CloseGreetingAccount`, returning the rent to the authority.
+    ///
+    /// Accounts expected: the same as `CreateGreeting`.
+    CreateGreetingWithExpiry {
+        name: String,
+        message: String,
+        index: u64,
+        expires_at: i64,
+    },
+
+    /// Creates the program's stats account, which counts greetings created and messages
+    /// set by instructions that were passed it. Anyone may call this, once.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The account paying for the stats account.
+    /// 1. `[writable]` The stats PDA, `derive_stats_pda(program_id)`.
+    /// 2. `[]` The System Program, required for creating accounts.
+    InitializeStats,
+
+    /// Sends `amount` lamports from the donor to a greeting account, as a tip, and adds
+    /// them to its `total_donations`. Anyone may donate to any greeting.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The donor, whose lamports are sent.
+    /// 1. `[writable]` The greeting account (PDA) receiving the donation.
+    /// 2. `[]` The System Program, used to transfer the lamports.
+    DonateToGreeting { amount: u64 },
+
+    /// Sends `amount` lamports from a greeting account to a destination, for collecting
+    /// donations. The greeting must stay rent exempt afterwards, or this fails with
+    /// `WouldBreakRentExemption`. Lowers `total_donations` by `amount`, stopping at zero.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to withdraw from.
+    /// 2. `[writable]` The account receiving the lamports.
+    WithdrawDonations { amount: u64 },
+
+    /// `CreateGreeting` for a greeting controlled by another program: the authority is a
+    /// PDA of that program rather than the payer, and the greeting and registry PDAs are
+    /// derived from it. The authority must be off the curve, so no keypair can stand in
+    /// for it, and must sign, which a PDA can only do when its program invokes this one
+    /// with `invoke_signed` and the PDA's seeds. Every later change goes the same way,
+    /// with the controlling program signing for the authority on each CPI; see
+    /// `cpi::create_greeting_with_pda_authority`.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The account paying for the greeting and registry rent.
+    /// 1. `[signer]` The authority, a PDA of the controlling program.
+    /// 2. `[writable]` The greeting PDA, `derive_greeting_pda(program_id, authority, index)`.
+    /// 3. `[]` The System Program, required for creating accounts.
+    /// 4. `[writable]` The authority's registry PDA, `derive_registry_pda(program_id, authority)`.
+    /// 5. `[writable]` Optional: the stats PDA, as for `CreateGreeting`.
+    CreateGreetingWithPdaAuthority {
+        name: String,
+        message: String,
+        index: u64,
+    },
+
+    /// `SetGreeting` that does nothing when `message` equals the current message: it
+    /// succeeds without writing the account, so `update_count`, `updated_at` and the
+    /// history stay as they were and the stats PDA isn't counted. The authority (and any
+    /// co-authority) must still sign.
+    ///
+    /// Accounts expected: the same as `SetGreeting`.
+    SetGreetingIfChanged { message: String },
+
+    /// Recomputes the canonical bump of the greeting's PDA with `find_program_address` and
+    /// stores it, for accounts whose `bump` predates bump storage and decodes as zero. The
+    /// seeds are those of `derive_greeting_pda(program_id, creator, index)`, or with `index`
+    /// of `None`, `derive_named_greeting_pda(program_id, creator, name)`. `creator` is the
+    /// key the greeting was created by, which differs from the current authority after a
+    /// `TransferAuthority`, and `name` the one it was created under, which differs from the
+    /// stored name after a rename; `None` falls back to the stored name. `name` is ignored
+    /// with an `index`. Fails with `PdaMismatch` if the seeds don't derive the greeting's
+    /// own address. Doesn't count as an update.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to repair.
+    RepairBump {
+        creator: Pubkey,
+        index: Option<u64>,
+        name: Option<String>,
+    },
+
+    /// `CreateGreeting` with the account sized to the new greeting rather than the largest
+    /// possible one: `exact_space_needed(name, message)` bytes instead of
+    /// `get_max_space_needed()`. A short greeting pays a fraction of the rent, but has no
+    /// room to change: every edit also records the old message in `history`, so editing
+    /// fails with `AccountDataTooSmall` until `GrowGreeting` makes room, at the authority's
+    /// expense then.
+    ///
+    /// Accounts expected: the same as `CreateGreeting`.
+    CreateCompactGreeting {
+        name: String,
+        message: String,
+        index: u64,
+    },
+
+    /// Adds `key` to the config's allowlist of greeting creators, see
+    /// `SetAllowlistEnabled`. Adding a key already on it changes nothing. Fails with
+    /// `AllowlistFull` once the allowlist holds `MAX_ALLOWLIST_LEN` keys.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The config admin, who pays to grow a config created before
+    ///    the allowlist existed.
+    /// 1. `[writable]` The config PDA.
+    /// 2. `[]` The System Program.
+    AddToAllowlist { key: Pubkey },
+
+    /// Removes `key` from the config's allowlist. Removing a key that isn't on it changes
+    /// nothing. Greetings the key already owns are unaffected.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The config admin.
+    /// 1. `[writable]` The config PDA.
+    RemoveFromAllowlist { key: Pubkey },
+
+    /// Turns the allowlist gate on or off; it starts off. While it is on, every
+    /// instruction that creates a greeting fails with `NotAllowlisted` unless the new
+    /// greeting's authority is on the allowlist: the payer, or the PDA authority for
+    /// `CreateGreetingWithPdaAuthority`. Only creation is gated.
+    ///
+    /// Accounts expected: the same as `AddToAllowlist`.
+    SetAllowlistEnabled { enabled: bool },
+
+    /// Sets the least time, in seconds, between two edits of one greeting's message,
+    /// counted from its `updated_at`. Every instruction that sets the message, batched,
+    /// appended and reset ones included, fails with `TooSoon` sooner than that. Zero, the
+    /// default, turns the limit off.
+    ///
+    /// Accounts expected: the same as `AddToAllowlist`.
+    SetMinUpdateInterval { secs: u32 },
+
+    /// Freezes the greeting for good. It stays readable, but every instruction that would
+    /// change it fails with `GreetingFrozen` from then on, closing it included, so its
+    /// rent stays locked in the account. There is no unfreeze.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account.
+    /// 1. `[writable]` The greeting account (PDA) to freeze.
+    /// 2. `[signer]` The co-authority, only if the greeting has one.
+    FreezeGreeting,
+
+    /// Closes a greeting account like `CloseGreetingAccount`, but only ever refunds its
+    /// lamports to its `rent_payer`, the account that paid its rent at creation. The
+    /// destination must be that account, whoever closes it. Greetings created before
+    /// `rent_payer` was recorded don't know theirs and can't be closed this way.
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of the greeting account. Once the greeting has expired this
+    ///    can be anyone, and needn't sign.
+    /// 1. `[writable]` The greeting account (PDA) to close.
+    /// 2. `[writable]` The greeting's `rent_payer`, which receives the reclaimed lamports.
+    /// 3. `[writable]` The registry PDA of the greeting's creator, as for `CloseGreetingAccount`.
+    /// 4. `[signer]` The co-authority, as for `CloseGreetingAccount`.
+    CloseGreetingToRentPayer,
+
+    /// Sends every lamport above the greeting's rent-exempt minimum, at its current size,
+    /// to a destination, leaving it funded exactly to exemption. For reclaiming lamports
+    /// from an account a client over-funded. Any donations are part of the surplus, so
+    /// `total_donations` drops by the amount swept, as for `WithdrawDonations`. Succeeds
+    /// without moving anything if there is no surplus.
+    ///
+    /// Accounts expected: the same as `WithdrawDonations`.
+    SweepSurplus,
+}
+
+impl GreetingInstruction {
+    /// Serializes the instruction into the bytes the program expects as instruction data.
+    pub fn pack(&self) -> Vec<u8> {
+        // Serializing into a `Vec` can't hit an I/O error, so this never fails in practice.
+        borsh::to_vec(self).expect("GreetingInstruction serializes into a Vec")
+    }
+
+    /// Decodes instruction data, failing with `InvalidInstructionData` if it doesn't hold
+    /// exactly one instruction, trailing bytes included. `SetGreeting` is also accepted
+    /// in its encoding from before `name`, the discriminant and message alone.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut remaining = data;
+        match Self::deserialize_compat(&mut remaining) {
+            Ok(instruction) if remaining.is_empty() => Ok(instruction),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    // Decodes one instruction off the front of `data` like `deserialize`, falling back to
+    // the older `SetGreeting` encoding without `name` when that fails. The new encoding
+    // always has a byte more, so no payload decodes both ways.
+    pub(crate) fn deserialize_compat(data: &mut &[u8]) -> borsh::io::Result<Self> {
+        let original = *data;
+        Self::deserialize(data).or_else(|err| match original.split_first() {
+            // SetGreeting's discriminant, pinned by `expected_discriminant`
+            Some((1, mut rest)) => {
+                let message = String::deserialize(&mut rest).map_err(|_| err)?;
+                *data = rest;
+                Ok(GreetingInstruction::SetGreeting {
+                    message,
+                    name: None,
+                })
+            }
+            _ => Err(err),
+        })
+    }
+
+    // Config and read-only instructions stay usable while paused; everything else is blocked.
+    pub(crate) fn is_pausable(&self) -> bool {
+        !matches!(
+            self,
+            GreetingInstruction::InitializeConfig { .. }
+                | GreetingInstruction::UpdateConfig { .. }
+                | GreetingInstruction::SetPaused { .. }
+                | GreetingInstruction::LogGreetingInfo
+                | GreetingInstruction::InitializeStats
+                | GreetingInstruction::AddToAllowlist { .. }
+                | GreetingInstruction::RemoveFromAllowlist { .. }
+                | GreetingInstruction::SetAllowlistEnabled { .. }
+                | GreetingInstruction::SetMinUpdateInterval { .. }
+        )
+    }
+
+    // Number of accounts listed under "Accounts expected" for each variant,
+    // plus the trailing config PDA for pausable ones.
+    pub(crate) fn account_count(&self) -> usize {
+        let listed = match self {
+            GreetingInstruction::CreateGreeting { .. } => 4,
+            GreetingInstruction::CreateGreetingWithBump { .. } => 4,
+            GreetingInstruction::SetGreeting { .. } => 2,
+            GreetingInstruction::CloseGreetingAccount => 4,
+            GreetingInstruction::TransferAuthority { .. } => 2,
+            // The config PDA is already listed for ResetGreeting
+            GreetingInstruction::ResetGreeting => 2,
+            GreetingInstruction::MigrateGreeting => 3,
+            GreetingInstruction::GrowGreeting { .. } => 4,
+            GreetingInstruction::AppendToMessage { .. } => 2,
+            GreetingInstruction::LikeGreeting => 2,
+            GreetingInstruction::InitializeConfig { .. } => 3,
+            GreetingInstruction::UpdateConfig { .. } => 2,
+            GreetingInstruction::SetPaused { .. } => 2,
+            GreetingInstruction::RenameGreeting { .. } => 2,
+            GreetingInstruction::SetVisibility { .. } => 2,
+            GreetingInstruction::CreateNamedGreeting { .. } => 4,
+            GreetingInstruction::BatchSetGreeting { messages } => 1 + messages.len(),
+            GreetingInstruction::SetOrCreateGreeting { .. } => 4,
+            GreetingInstruction::LogGreetingInfo => 1,
+            GreetingInstruction::SetCoAuthority { .. } => 2,
+            GreetingInstruction::SetGreetingChecked { .. } => 2,
+            GreetingInstruction::CreateGreetingWithExpiry { .. } => 4,
+            GreetingInstruction::InitializeStats => 3,
+            GreetingInstruction::DonateToGreeting { .. } => 3,
+            GreetingInstruction::WithdrawDonations { .. } => 3,
+            GreetingInstruction::CreateGreetingWithPdaAuthority { .. } => 5,
+            GreetingInstruction::SetGreetingIfChanged { .. } => 2,
+            GreetingInstruction::RepairBump { .. } => 2,
+            GreetingInstruction::CreateCompactGreeting { .. } => 4,
+            GreetingInstruction::AddToAllowlist { .. } => 3,
+            GreetingInstruction::RemoveFromAllowlist { .. } => 2,
+            GreetingInstruction::SetAllowlistEnabled { .. } => 3,
+            GreetingInstruction::SetMinUpdateInterval { .. } => 3,
+            GreetingInstruction::FreezeGreeting => 2,
+            GreetingInstruction::CloseGreetingToRentPayer => 4,
+            GreetingInstruction::SweepSurplus => 3,
+        };
+        listed + usize::from(self.is_pausable())
+    }
+}
+
+#[cfg(feature = "strict")]
+pub(crate) mod strict {
+    use super::GreetingInstruction;
+
+    // The flags an account is allowed to carry; see the `strict` notes on `GreetingInstruction`.
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) struct AccountFlags {
+        pub(crate) signer: bool,
+        pub(crate) writable: bool,
+    }
+
+    const READONLY: AccountFlags = AccountFlags {
+        signer: false,
+        writable: false,
+    };
+    const WRITABLE: AccountFlags = AccountFlags {
+        signer: false,
+        writable: true,
+    };
+    const SIGNER: AccountFlags = AccountFlags {
+        signer: true,
+        writable: false,
+    };
+    const SIGNER_WRITABLE: AccountFlags = AccountFlags {
+        signer: true,
+        writable: true,
+    };
+
+    impl GreetingInstruction {
+        // The flags allowed on each account listed under "Accounts expected", then those
+        // allowed on the optional accounts that may follow them. Mirrors `account_count`.
+        pub(crate) fn allowed_flags(&self) -> (Vec<AccountFlags>, &'static [AccountFlags]) {
+            match self {
+                GreetingInstruction::CreateGreeting { .. }
+                | GreetingInstruction::CreateGreetingWithBump { .. }
+                | GreetingInstruction::CreateNamedGreeting { .. }
+                | GreetingInstruction::CreateGreetingWithExpiry { .. }
+                | GreetingInstruction::CreateCompactGreeting { .. } => (
+                    vec![SIGNER_WRITABLE, WRITABLE, READONLY, WRITABLE],
+                    &[WRITABLE],
+                ),
+                GreetingInstruction::SetOrCreateGreeting { .. } => (
+                    vec![SIGNER_WRITABLE, WRITABLE, READONLY, WRITABLE],
+                    &[SIGNER, WRITABLE],
+                ),
+                GreetingInstruction::CreateGreetingWithPdaAuthority { .. } => (
+                    vec![SIGNER_WRITABLE, SIGNER, WRITABLE, READONLY, WRITABLE],
+                    &[WRITABLE],
+                ),
+                GreetingInstruction::SetGreeting { .. }
+                | GreetingInstruction::ResetGreeting
+                | GreetingInstruction::SetGreetingChecked { .. }
+                | GreetingInstruction::SetGreetingIfChanged { .. } => {
+                    (vec![SIGNER, WRITABLE], &[SIGNER, WRITABLE])
+                }
+                // The destination is usually the fee payer, which always signs
+                GreetingInstruction::CloseGreetingAccount
+                | GreetingInstruction::CloseGreetingToRentPayer => {
+                    (vec![SIGNER, WRITABLE, SIGNER_WRITABLE, WRITABLE], &[SIGNER])
+                }
+                GreetingInstruction::WithdrawDonations { .. }
+                | GreetingInstruction::SweepSurplus => {
+                    (vec![SIGNER, WRITABLE, SIGNER_WRITABLE], &[])
+                }
+                GreetingInstruction::MigrateGreeting
+                | GreetingInstruction::DonateToGreeting { .. }
+                | GreetingInstruction::InitializeConfig { .. }
+                | GreetingInstruction::InitializeStats
+                | GreetingInstruction::AddToAllowlist { .. }
+                | GreetingInstruction::SetAllowlistEnabled { .. }
+                | GreetingInstruction::SetMinUpdateInterval { .. } => {
+                    (vec![SIGNER_WRITABLE, WRITABLE, READONLY], &[])
+                }
+                GreetingInstruction::GrowGreeting { .. } => {
+                    (vec![SIGNER, WRITABLE, SIGNER_WRITABLE, READONLY], &[])
+                }
+                GreetingInstruction::BatchSetGreeting { messages } => {
+                    let mut flags = vec![SIGNER];
+                    flags.extend(messages.iter().map(|_| WRITABLE));
+                    (flags, &[SIGNER])
+                }
+                GreetingInstruction::LogGreetingInfo => (vec![READONLY], &[]),
+                GreetingInstruction::SetCoAuthority { .. }
+                | GreetingInstruction::FreezeGreeting
+                | GreetingInstruction::AppendToMessage { .. }
+                | GreetingInstruction::RenameGreeting { .. }
+                | GreetingInstruction::TransferAuthority { .. }
+                | GreetingInstruction::SetVisibility { .. } => (vec![SIGNER, WRITABLE], &[SIGNER]),
+                GreetingInstruction::LikeGreeting
+                | GreetingInstruction::UpdateConfig { .. }
+                | GreetingInstruction::SetPaused { .. }
+                | GreetingInstruction::RemoveFromAllowlist { .. }
+                | GreetingInstruction::RepairBump { .. } => (vec![SIGNER, WRITABLE], &[]),
+            }
+        }
+    }
+
+    impl AccountFlags {
+        // A signer may always be writable too, since the fee payer always is.
+        pub(crate) fn allow(&self, is_signer: bool, is_writable: bool) -> bool {
+            (!is_signer || self.signer) && (!is_writable || self.writable || is_signer)
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+mod builders {
+    use solana_program::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+    };
+
+    use super::accounts::{
+        BatchSetGreetingAccounts, CloseGreetingAccounts, ConfigAccounts, CreateGreetingAccounts,
+        CreateGreetingWithPdaAuthorityAccounts, FundGreetingAccounts, GreetingAuthorityAccounts,
+        GrowGreetingAccounts, InitializeStatsAccounts, LogGreetingInfoAccounts,
+        WithdrawDonationsAccounts,
+    };
+    use super::GreetingInstruction;
+    use crate::state::{
+        derive_greeting_pda, derive_named_greeting_pda, derive_registry_pda, derive_stats_pda,
+    };
+
+    // The account lists come from `accounts`, which also appends the config PDA to those
+    // of pausable instructions, since the program checks it last.
+    fn build(
+        program_id: &Pubkey,
+        instruction: &GreetingInstruction,
+        accounts: Vec<AccountMeta>,
+    ) -> Instruction {
+        Instruction::new_with_bytes(*program_id, &instruction.pack(), accounts)
+    }
+
+    /// Creates the `index`-th greeting owned by `payer`, deriving its PDA.
+    pub fn create_greeting(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        name: String,
+        message: String,
+        index: u64,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_greeting_pda(program_id, payer, index);
+        build(
+            program_id,
+            &GreetingInstruction::CreateGreeting {
+                name,
+                message,
+                index,
+            },
+            CreateGreetingAccounts::new(program_id, payer, &greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Creates the `index`-th greeting owned by `payer`, passing `bump` so the program can
+    /// skip the PDA search. Use the bump returned by `derive_greeting_pda`.
+    pub fn create_greeting_with_bump(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        name: String,
+        message: String,
+        index: u64,
+        bump: u8,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_greeting_pda(program_id, payer, index);
+        build(
+            program_id,
+            &GreetingInstruction::CreateGreetingWithBump {
+                name,
+                message,
+                index,
+                bump,
+            },
+            CreateGreetingAccounts::new(program_id, payer, &greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Creates the `index`-th greeting owned by `payer` in an account sized to fit just
+    /// this greeting, see `GreetingInstruction::CreateCompactGreeting`.
+    pub fn create_compact_greeting(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        name: String,
+        message: String,
+        index: u64,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_greeting_pda(program_id, payer, index);
+        build(
+            program_id,
+            &GreetingInstruction::CreateCompactGreeting {
+                name,
+                message,
+                index,
+            },
+            CreateGreetingAccounts::new(program_id, payer, &greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Creates the `index`-th greeting owned by `payer`, expiring at unix time `expires_at`.
+    pub fn create_greeting_with_expiry(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        name: String,
+        message: String,
+        index: u64,
+        expires_at: i64,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_greeting_pda(program_id, payer, index);
+        build(
+            program_id,
+            &GreetingInstruction::CreateGreetingWithExpiry {
+                name,
+                message,
+                index,
+                expires_at,
+            },
+            CreateGreetingAccounts::new(program_id, payer, &greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Creates the `index`-th greeting owned by `authority`, a PDA of another program, with
+    /// `payer` funding it. Only that program can sign for `authority`, so this is for
+    /// building the instruction it passes to `invoke_signed`.
+    pub fn create_greeting_with_pda_authority(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        authority: &Pubkey,
+        name: String,
+        message: String,
+        index: u64,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_greeting_pda(program_id, authority, index);
+        build(
+            program_id,
+            &GreetingInstruction::CreateGreetingWithPdaAuthority {
+                name,
+                message,
+                index,
+            },
+            CreateGreetingWithPdaAuthorityAccounts::new(
+                program_id,
+                payer,
+                authority,
+                &greeting_pda,
+            )
+            .to_account_metas(),
+        )
+    }
+
+    /// Creates the greeting named `name` owned by `payer`, at its name-derived PDA.
+    pub fn create_named_greeting(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        name: String,
+        message: String,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_named_greeting_pda(program_id, payer, &name);
+        build(
+            program_id,
+            &GreetingInstruction::CreateNamedGreeting { name, message },
+            CreateGreetingAccounts::new(program_id, payer, &greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Creates the greeting named `name` owned by `payer`, or sets `message` on it if it exists.
+    pub fn set_or_create_greeting(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        name: String,
+        message: String,
+    ) -> Instruction {
+        let (greeting_pda, _) = derive_named_greeting_pda(program_id, payer, &name);
+        build(
+            program_id,
+            &GreetingInstruction::SetOrCreateGreeting { name, message },
+            CreateGreetingAccounts::new(program_id, payer, &greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Logs the state and account details of `greeting_pda`, for debugging.
+    pub fn log_greeting_info(program_id: &Pubkey, greeting_pda: &Pubkey) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::LogGreetingInfo,
+            LogGreetingInfoAccounts {
+                greeting_pda: *greeting_pda,
+            }
+            .to_account_metas(),
+        )
+    }
+
+    /// Sets each message on its greeting in one instruction, signed by their shared `authority`.
+    pub fn batch_set_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        updates: Vec<(Pubkey, String)>,
+    ) -> Instruction {
+        let (greeting_pdas, messages) = updates.into_iter().unzip();
+        build(
+            program_id,
+            &GreetingInstruction::BatchSetGreeting { messages },
+            BatchSetGreetingAccounts::new(program_id, authority, greeting_pdas).to_account_metas(),
+        )
+    }
+
+    /// Sets a new message on `greeting_pda`, signed by its `authority`.
+    pub fn set_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        message: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetGreeting {
+                message,
+                name: None,
+            },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Sets a new message on `greeting_pda` and renames it in one edit, signed by its
+    /// `authority`.
+    pub fn set_greeting_and_name(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        message: String,
+        name: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetGreeting {
+                message,
+                name: Some(name),
+            },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Sets a new message on `greeting_pda`, but only if its `update_count` is still
+    /// `expected_update_count`.
+    pub fn set_greeting_checked(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        message: String,
+        expected_update_count: u32,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetGreetingChecked {
+                message,
+                expected_update_count,
+            },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Sets a new message on `greeting_pda`, leaving it untouched if it already has it.
+    pub fn set_greeting_if_changed(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        message: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetGreetingIfChanged { message },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Rewrites the stored bump of `greeting_pda`, created by `creator` at `index`, or
+    /// under `name` with `index` of `None`.
+    pub fn repair_bump(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        creator: &Pubkey,
+        index: Option<u64>,
+        name: Option<String>,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::RepairBump {
+                creator: *creator,
+                index,
+                name,
+            },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Sets a new message on `greeting_pda` that has a co-authority, signed by both.
+    pub fn set_greeting_with_co_authority(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        co_authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        message: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetGreeting {
+                message,
+                name: None,
+            },
+            GreetingAuthorityAccounts {
+                co_authority: Some(*co_authority),
+                ..GreetingAuthorityAccounts::new(program_id, authority, greeting_pda)
+            }
+            .to_account_metas(),
+        )
+    }
+
+    /// Sets or clears the co-authority of `greeting_pda`. `current_co_authority` must be
+    /// passed, and sign, if the greeting already has one.
+    pub fn set_co_authority(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        current_co_authority: Option<&Pubkey>,
+        co_authority: Option<Pubkey>,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetCoAuthority { co_authority },
+            GreetingAuthorityAccounts {
+                co_authority: current_co_authority.copied(),
+                ..GreetingAuthorityAccounts::new(program_id, authority, greeting_pda)
+            }
+            .to_account_metas(),
+        )
+    }
+
+    /// Closes `greeting_pda`, sending its lamports to `destination`.
+    pub fn close_greeting_account(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        destination: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::CloseGreetingAccount,
+            CloseGreetingAccounts::new(program_id, authority, greeting_pda, destination)
+                .to_account_metas(),
+        )
+    }
+
+    /// Closes the expired `greeting_pda` on behalf of anyone, `closer`, sending its lamports
+    /// to its `authority`. `closer` doesn't need to sign.
+    pub fn close_expired_greeting(
+        program_id: &Pubkey,
+        closer: &Pubkey,
+        greeting_pda: &Pubkey,
+        authority: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::CloseGreetingAccount,
+            CloseGreetingAccounts::expired(program_id, closer, greeting_pda, authority)
+                .to_account_metas(),
+        )
+    }
+
+    /// Closes `greeting_pda`, which `authority` received through TransferAuthority from its
+    /// `creator`, sending its lamports to `destination`. The slot goes back to the creator's
+    /// registry.
+    pub fn close_transferred_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        creator: &Pubkey,
+        greeting_pda: &Pubkey,
+        destination: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::CloseGreetingAccount,
+            CloseGreetingAccounts {
+                registry: derive_registry_pda(program_id, creator).0,
+                ..CloseGreetingAccounts::new(program_id, authority, greeting_pda, destination)
+            }
+            .to_account_metas(),
+        )
+    }
+
+    /// Hands control of `greeting_pda` from `authority` to `new_authority`. `co_authority`
+    /// must be passed, and sign, if the greeting has one.
+    pub fn transfer_authority(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        co_authority: Option<&Pubkey>,
+        new_authority: Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::TransferAuthority { new_authority },
+            GreetingAuthorityAccounts {
+                co_authority: co_authority.copied(),
+                ..GreetingAuthorityAccounts::new(program_id, authority, greeting_pda)
+            }
+            .to_account_metas(),
+        )
+    }
+
+    /// Resets the message on `greeting_pda` to the default, taken from the config if it exists.
+    pub fn reset_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::ResetGreeting,
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Migrates `greeting_pda` to the current layout, with `authority` paying any extra rent.
+    pub fn migrate_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::MigrateGreeting,
+            FundGreetingAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Grows `greeting_pda` to `new_size` bytes, with `payer` covering the extra rent.
+    pub fn grow_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        payer: &Pubkey,
+        new_size: u64,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::GrowGreeting { new_size },
+            GrowGreetingAccounts::new(program_id, authority, greeting_pda, payer)
+                .to_account_metas(),
+        )
+    }
+
+    /// Appends `suffix` to the message on `greeting_pda`.
+    pub fn append_to_message(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        suffix: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::AppendToMessage { suffix },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Likes `greeting_pda` on behalf of `liker`.
+    pub fn like_greeting(
+        program_id: &Pubkey,
+        liker: &Pubkey,
+        greeting_pda: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::LikeGreeting,
+            GreetingAuthorityAccounts::new(program_id, liker, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Creates the program's config account with `payer` as admin.
+    pub fn initialize_config(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        default_message: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::InitializeConfig { default_message },
+            ConfigAccounts::paid(program_id, payer).to_account_metas(),
+        )
+    }
+
+    /// Sends `amount` lamports from `donor` to `greeting_pda`.
+    pub fn donate_to_greeting(
+        program_id: &Pubkey,
+        donor: &Pubkey,
+        greeting_pda: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::DonateToGreeting { amount },
+            FundGreetingAccounts::new(program_id, donor, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Sends `amount` lamports from `greeting_pda` to `destination`, signed by its `authority`.
+    pub fn withdraw_donations(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::WithdrawDonations { amount },
+            WithdrawDonationsAccounts::new(program_id, authority, greeting_pda, destination)
+                .to_account_metas(),
+        )
+    }
+
+    /// Sends everything above the rent-exempt minimum in `greeting_pda` to `destination`,
+    /// signed by its `authority`.
+    pub fn sweep_surplus(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        destination: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SweepSurplus,
+            WithdrawDonationsAccounts::new(program_id, authority, greeting_pda, destination)
+                .to_account_metas(),
+        )
+    }
+
+    /// Creates the program's stats account, paid for by `payer`.
+    pub fn initialize_stats(program_id: &Pubkey, payer: &Pubkey) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::InitializeStats,
+            InitializeStatsAccounts::new(program_id, payer).to_account_metas(),
+        )
+    }
+
+    /// Adds the stats PDA to a create or `SetGreeting` instruction built above, just before
+    /// the trailing config PDA, so the program counts it.
+    pub fn with_stats(program_id: &Pubkey, mut instruction: Instruction) -> Instruction {
+        let config_index = instruction.accounts.len().saturating_sub(1);
+        instruction.accounts.insert(
+            config_index,
+            AccountMeta::new(derive_stats_pda(program_id).0, false),
+        );
+        instruction
+    }
+
+    /// Changes the config's default message, signed by its `admin`.
+    pub fn update_config(
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        default_message: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::UpdateConfig { default_message },
+            ConfigAccounts::new(program_id, admin).to_account_metas(),
+        )
+    }
+
+    /// Renames `greeting_pda`, signed by its `authority`.
+    pub fn rename_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        name: String,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::RenameGreeting { name },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Makes `greeting_pda` public or private, signed by its `authority`.
+    pub fn set_visibility(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        is_public: bool,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetVisibility { is_public },
+            GreetingAuthorityAccounts::new(program_id, authority, greeting_pda).to_account_metas(),
+        )
+    }
+
+    /// Pauses or unpauses greeting changes, signed by the config `admin`.
+    pub fn set_paused(program_id: &Pubkey, admin: &Pubkey, paused: bool) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetPaused { paused },
+            ConfigAccounts::new(program_id, admin).to_account_metas(),
+        )
+    }
+
+    /// Allows `key` to create greetings while the allowlist gate is on, signed by the
+    /// config `admin`.
+    pub fn add_to_allowlist(program_id: &Pubkey, admin: &Pubkey, key: &Pubkey) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::AddToAllowlist { key: *key },
+            ConfigAccounts::paid(program_id, admin).to_account_metas(),
+        )
+    }
+
+    /// Takes `key` off the allowlist, signed by the config `admin`.
+    pub fn remove_from_allowlist(program_id: &Pubkey, admin: &Pubkey, key: &Pubkey) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::RemoveFromAllowlist { key: *key },
+            ConfigAccounts::new(program_id, admin).to_account_metas(),
+        )
+    }
+
+    /// Turns the allowlist gate on greeting creation on or off, signed by the config `admin`.
+    pub fn set_allowlist_enabled(
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        enabled: bool,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetAllowlistEnabled { enabled },
+            ConfigAccounts::paid(program_id, admin).to_account_metas(),
+        )
+    }
+
+    /// Freezes `greeting_pda` for good, signed by its `authority`. A greeting with a
+    /// co-authority also needs it to sign, passed as `co_authority`.
+    pub fn freeze_greeting(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        co_authority: Option<&Pubkey>,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::FreezeGreeting,
+            GreetingAuthorityAccounts {
+                co_authority: co_authority.copied(),
+                ..GreetingAuthorityAccounts::new(program_id, authority, greeting_pda)
+            }
+            .to_account_metas(),
+        )
+    }
+
+    /// Closes `greeting_pda`, refunding its lamports to `rent_payer`, the account that paid
+    /// for it.
+    pub fn close_greeting_to_rent_payer(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        rent_payer: &Pubkey,
+    ) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::CloseGreetingToRentPayer,
+            CloseGreetingAccounts::new(program_id, authority, greeting_pda, rent_payer)
+                .to_account_metas(),
+        )
+    }
+
+    /// Sets the least time between SetGreeting edits of one greeting, signed by the
+    /// config `admin`. Zero turns the limit off.
+    pub fn set_min_update_interval(program_id: &Pubkey, admin: &Pubkey, secs: u32) -> Instruction {
+        build(
+            program_id,
+            &GreetingInstruction::SetMinUpdateInterval { secs },
+            ConfigAccounts::paid(program_id, admin).to_account_metas(),
+        )
+    }
+
+    /// The addresses of `authority`'s indexed greetings `0..count`, in index order.
+    ///
+    /// Pass the `next_index` from `authority`'s registry (see `derive_registry_pda`) to
+    /// cover every index it has used. Closed greetings and skipped indices have no
+    /// account, so fetch each address and drop the missing ones. Named greetings aren't
+    /// indexed; derive those with `derive_named_greeting_pda`.
+    pub fn greeting_pdas_for(program_id: &Pubkey, authority: &Pubkey, count: u64) -> Vec<Pubkey> {
+        (0..count)
+            .map(|index| derive_greeting_pda(program_id, authority, index).0)
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::state::derive_config_pda;
+        use borsh::BorshDeserialize;
+        use solana_program::system_program;
+
+        // Every builder passes the accounts its instruction lists, plus the trailing
+        // config for pausable ones, and with `strict` none carries a flag it may not.
+        #[test]
+        fn test_builders_pass_the_accounts_each_instruction_expects() {
+            let program_id = Pubkey::new_unique();
+            let key = Pubkey::new_unique();
+            let co_authority = Pubkey::new_unique();
+            let greeting_pda = derive_greeting_pda(&program_id, &key, 0).0;
+            let text = || "Hello".to_string();
+            let instructions = [
+                create_greeting(&program_id, &key, text(), text(), 0),
+                create_greeting_with_bump(&program_id, &key, text(), text(), 0, 255),
+                create_compact_greeting(&program_id, &key, text(), text(), 0),
+                create_greeting_with_expiry(&program_id, &key, text(), text(), 0, 60),
+                create_greeting_with_pda_authority(&program_id, &key, &key, text(), text(), 0),
+                create_named_greeting(&program_id, &key, text(), text()),
+                set_or_create_greeting(&program_id, &key, text(), text()),
+                log_greeting_info(&program_id, &greeting_pda),
+                batch_set_greeting(&program_id, &key, vec![(greeting_pda, text()); 2]),
+                set_greeting(&program_id, &key, &greeting_pda, text()),
+                set_greeting_and_name(&program_id, &key, &greeting_pda, text(), text()),
+                set_greeting_checked(&program_id, &key, &greeting_pda, text(), 0),
+                set_greeting_if_changed(&program_id, &key, &greeting_pda, text()),
+                repair_bump(&program_id, &key, &greeting_pda, &key, Some(0), None),
+                set_greeting_with_co_authority(
+                    &program_id,
+                    &key,
+                    &co_authority,
+                    &greeting_pda,
+                    text(),
+                ),
+                set_co_authority(&program_id, &key, &greeting_pda, Some(&co_authority), None),
+                close_greeting_account(&program_id, &key, &greeting_pda, &key),
+                close_expired_greeting(&program_id, &key, &greeting_pda, &key),
+                close_transferred_greeting(&program_id, &key, &co_authority, &greeting_pda, &key),
+                transfer_authority(&program_id, &key, &greeting_pda, Some(&co_authority), key),
+                reset_greeting(&program_id, &key, &greeting_pda),
+                migrate_greeting(&program_id, &key, &greeting_pda),
+                grow_greeting(&program_id, &key, &greeting_pda, &key, 4096),
+                append_to_message(&program_id, &key, &greeting_pda, text()),
+                like_greeting(&program_id, &key, &greeting_pda),
+                initialize_config(&program_id, &key, text()),
+                donate_to_greeting(&program_id, &key, &greeting_pda, 1),
+                withdraw_donations(&program_id, &key, &greeting_pda, &key, 1),
+                initialize_stats(&program_id, &key),
+                update_config(&program_id, &key, text()),
+                rename_greeting(&program_id, &key, &greeting_pda, text()),
+                set_visibility(&program_id, &key, &greeting_pda, false),
+                set_paused(&program_id, &key, true),
+                add_to_allowlist(&program_id, &key, &key),
+                remove_from_allowlist(&program_id, &key, &key),
+                set_allowlist_enabled(&program_id, &key, true),
+                set_min_update_interval(&program_id, &key, 60),
+                freeze_greeting(&program_id, &key, &greeting_pda, Some(&co_authority)),
+                close_greeting_to_rent_payer(&program_id, &key, &greeting_pda, &key),
+                sweep_surplus(&program_id, &key, &greeting_pda, &key),
+            ];
+
+            for instruction in instructions {
+                let decoded = GreetingInstruction::unpack(&instruction.data).unwrap();
+                // A co-authority is the one optional account these pass
+                let optional = instruction
+                    .accounts
+                    .iter()
+                    .filter(|meta| meta.pubkey == co_authority)
+                    .count();
+                assert_eq!(
+                    instruction.accounts.len(),
+                    decoded.account_count() + optional,
+                    "{:?}",
+                    decoded
+                );
+                if decoded.is_pausable() {
+                    assert_eq!(
+                        instruction.accounts.last(),
+                        Some(&AccountMeta::new_readonly(
+                            derive_config_pda(&program_id).0,
+                            false
+                        ))
+                    );
+                }
+                #[cfg(feature = "strict")]
+                {
+                    let (listed, optional) = decoded.allowed_flags();
+                    for (i, meta) in instruction.accounts.iter().enumerate() {
+                        let allowed = match listed.get(i) {
+                            Some(flags) => std::slice::from_ref(flags),
+                            // The trailing config is passed read-only
+                            None if decoded.is_pausable()
+                                && i == instruction.accounts.len() - 1 =>
+                            {
+                                continue
+                            }
+                            None => optional,
+                        };
+                        assert!(
+                            allowed
+                                .iter()
+                                .any(|flags| flags.allow(meta.is_signer, meta.is_writable)),
+                            "{:?} account {}",
+                            decoded,
+                            i
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn test_greeting_pdas_for_matches_derivation() {
+            let program_id = Pubkey::new_unique();
+            let authority = Pubkey::new_unique();
+            let pdas = greeting_pdas_for(&program_id, &authority, 5);
+
+            assert_eq!(pdas.len(), 5);
+            for (index, pda) in pdas.iter().enumerate() {
+                assert_eq!(
+                    *pda,
+                    derive_greeting_pda(&program_id, &authority, index as u64).0
+                );
+            }
+            assert!(greeting_pdas_for(&program_id, &authority, 0).is_empty());
+            // Another authority's greetings never collide
+            assert_ne!(
+                greeting_pdas_for(&program_id, &Pubkey::new_unique(), 1)[0],
+                pdas[0]
+            );
+        }
+
+        #[test]
+        fn test_create_greeting_round_trips() {
+            let program_id = Pubkey::new_unique();
+            let payer = Pubkey::new_unique();
+            let instruction = create_greeting(
+                &program_id,
+                &payer,
+                "Alice".to_string(),
+                "Hello".to_string(),
+                3,
+            );
+
+            match GreetingInstruction::try_from_slice(&instruction.data).unwrap() {
+                GreetingInstruction::CreateGreeting {
+                    name,
+                    message,
+                    index,
+                } => {
+                    assert_eq!(name, "Alice");
+                    assert_eq!(message, "Hello");
+                    assert_eq!(index, 3);
+                }
+                other => panic!("unexpected instruction {:?}", other),
+            }
+            assert_eq!(instruction.accounts[0], AccountMeta::new(payer, true));
+            assert_eq!(
+                instruction.accounts[1].pubkey,
+                derive_greeting_pda(&program_id, &payer, 3).0
+            );
+            assert_eq!(instruction.accounts[2].pubkey, system_program::ID);
+        }
+
+        #[test]
+        fn test_set_greeting_round_trips() {
+            let program_id = Pubkey::new_unique();
+            let authority = Pubkey::new_unique();
+            let greeting_pda = Pubkey::new_unique();
+            let instruction =
+                set_greeting(&program_id, &authority, &greeting_pda, "Hi".to_string());
+
+            match GreetingInstruction::try_from_slice(&instruction.data).unwrap() {
+                GreetingInstruction::SetGreeting { message, name } => {
+                    assert_eq!(message, "Hi");
+                    assert_eq!(name, None);
+                }
+                other => panic!("unexpected instruction {:?}", other),
+            }
+            assert_eq!(
+                instruction.accounts[0],
+                AccountMeta::new_readonly(authority, true)
+            );
+            assert_eq!(
+                instruction.accounts[1],
+                AccountMeta::new(greeting_pda, false)
+            );
+            assert_eq!(
+                instruction.accounts[2],
+                AccountMeta::new_readonly(derive_config_pda(&program_id).0, false)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::state::{
+        tests::{pubkey, text},
+        GreetingAccountState,
+    };
+    use proptest::prelude::*;
+
+    fn greeting_instruction() -> impl Strategy<Value = GreetingInstruction> {
+        let name = || text(GreetingAccountState::MAX_NAME_LENGTH);
+        let message = || text(GreetingAccountState::MAX_MESSAGE_CHARS);
+        prop_oneof![
+            (name(), message(), any::<u64>()).prop_map(|(name, message, index)| {
+                GreetingInstruction::CreateGreeting {
+                    name,
+                    message,
+                    index,
+                }
+            }),
+            (message(), proptest::option::of(name()))
+                .prop_map(|(message, name)| GreetingInstruction::SetGreeting { message, name }),
+            Just(GreetingInstruction::CloseGreetingAccount),
+            pubkey().prop_map(|new_authority| {
+                GreetingInstruction::TransferAuthority { new_authority }
+            }),
+            Just(GreetingInstruction::ResetGreeting),
+            Just(GreetingInstruction::MigrateGreeting),
+            any::<u64>().prop_map(|new_size| GreetingInstruction::GrowGreeting { new_size }),
+            message().prop_map(|suffix| GreetingInstruction::AppendToMessage { suffix }),
+            Just(GreetingInstruction::LikeGreeting),
+            message().prop_map(|default_message| {
+                GreetingInstruction::InitializeConfig { default_message }
+            }),
+            message().prop_map(|default_message| {
+                GreetingInstruction::UpdateConfig { default_message }
+            }),
+            any::<bool>().prop_map(|paused| GreetingInstruction::SetPaused { paused }),
+            name().prop_map(|name| GreetingInstruction::RenameGreeting { name }),
+            (name(), message(), any::<u64>(), any::<u8>()).prop_map(
+                |(name, message, index, bump)| GreetingInstruction::CreateGreetingWithBump {
+                    name,
+                    message,
+                    index,
+                    bump,
+                }
+            ),
+            any::<bool>().prop_map(|is_public| GreetingInstruction::SetVisibility { is_public }),
+            (name(), message()).prop_map(|(name, message)| {
+                GreetingInstruction::CreateNamedGreeting { name, message }
+            }),
+            proptest::collection::vec(message(), 0..8)
+                .prop_map(|messages| GreetingInstruction::BatchSetGreeting { messages }),
+            (name(), message()).prop_map(|(name, message)| {
+                GreetingInstruction::SetOrCreateGreeting { name, message }
+            }),
+            Just(GreetingInstruction::LogGreetingInfo),
+            proptest::option::of(pubkey())
+                .prop_map(|co_authority| GreetingInstruction::SetCoAuthority { co_authority }),
+            (message(), any::<u32>()).prop_map(|(message, expected_update_count)| {
+                GreetingInstruction::SetGreetingChecked {
+                    message,
+                    expected_update_count,
+                }
+            }),
+            (name(), message(), any::<u64>(), any::<i64>()).prop_map(
+                |(name, message, index, expires_at)| {
+                    GreetingInstruction::CreateGreetingWithExpiry {
+                        name,
+                        message,
+                        index,
+                        expires_at,
+                    }
+                }
+            ),
+            Just(GreetingInstruction::InitializeStats),
+            any::<u64>().prop_map(|amount| GreetingInstruction::DonateToGreeting { amount }),
+            any::<u64>().prop_map(|amount| GreetingInstruction::WithdrawDonations { amount }),
+            (name(), message(), any::<u64>()).prop_map(|(name, message, index)| {
+                GreetingInstruction::CreateGreetingWithPdaAuthority {
+                    name,
+                    message,
+                    index,
+                }
+            }),
+            message().prop_map(|message| GreetingInstruction::SetGreetingIfChanged { message }),
+            (
+                pubkey(),
+                proptest::option::of(any::<u64>()),
+                proptest::option::of(name())
+            )
+                .prop_map(|(creator, index, name)| GreetingInstruction::RepairBump {
+                    creator,
+                    index,
+                    name
+                }),
+            (name(), message(), any::<u64>()).prop_map(|(name, message, index)| {
+                GreetingInstruction::CreateCompactGreeting {
+                    name,
+                    message,
+                    index,
+                }
+            }),
+            pubkey().prop_map(|key| GreetingInstruction::AddToAllowlist { key }),
+            pubkey().prop_map(|key| GreetingInstruction::RemoveFromAllowlist { key }),
+            any::<bool>().prop_map(|enabled| GreetingInstruction::SetAllowlistEnabled { enabled }),
+            any::<u32>().prop_map(|secs| GreetingInstruction::SetMinUpdateInterval { secs }),
+            Just(GreetingInstruction::FreezeGreeting),
+            Just(GreetingInstruction::CloseGreetingToRentPayer),
+            Just(GreetingInstruction::SweepSurplus),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_instruction_round_trips(instruction in greeting_instruction()) {
+            let bytes = borsh::to_vec(&instruction).unwrap();
+            prop_assert_eq!(GreetingInstruction::try_from_slice(&bytes).unwrap(), instruction);
+        }
+    }
+
+    // The wire index of every variant, in one place. Deployed clients encode these bytes,
+    // so existing entries must never change; new variants are appended with the next index.
+    // The match is exhaustive, so adding a variant won't compile until it is listed here.
+    fn expected_discriminant(instruction: &GreetingInstruction) -> u8 {
+        match instruction {
+            GreetingInstruction::CreateGreeting { .. } => 0,
+            GreetingInstruction::SetGreeting { .. } => 1,
+            GreetingInstruction::CloseGreetingAccount => 2,
+            GreetingInstruction::TransferAuthority { .. } => 3,
+            GreetingInstruction::ResetGreeting => 4,
+            GreetingInstruction::MigrateGreeting => 5,
+            GreetingInstruction::GrowGreeting { .. } => 6,
+            GreetingInstruction::AppendToMessage { .. } => 7,
+            GreetingInstruction::LikeGreeting => 8,
+            GreetingInstruction::InitializeConfig { .. } => 9,
+            GreetingInstruction::UpdateConfig { .. } => 10,
+            GreetingInstruction::SetPaused { .. } => 11,
+            GreetingInstruction::RenameGreeting { .. } => 12,
+            GreetingInstruction::CreateGreetingWithBump { .. } => 13,
+            GreetingInstruction::SetVisibility { .. } => 14,
+            GreetingInstruction::CreateNamedGreeting { .. } => 15,
+            GreetingInstruction::BatchSetGreeting { .. } => 16,
+            GreetingInstruction::SetOrCreateGreeting { .. } => 17,
+            GreetingInstruction::LogGreetingInfo => 18,
+            GreetingInstruction::SetCoAuthority { .. } => 19,
+            GreetingInstruction::SetGreetingChecked { .. } => 20,
+            GreetingInstruction::CreateGreetingWithExpiry { .. } => 21,
+            GreetingInstruction::InitializeStats => 22,
+            GreetingInstruction::DonateToGreeting { .. } => 23,
+            GreetingInstruction::WithdrawDonations { .. } => 24,
+            GreetingInstruction::CreateGreetingWithPdaAuthority { .. } => 25,
+            GreetingInstruction::SetGreetingIfChanged { .. } => 26,
+            GreetingInstruction::RepairBump { .. } => 27,
+            GreetingInstruction::CreateCompactGreeting { .. } => 28,
+            GreetingInstruction::AddToAllowlist { .. } => 29,
+            GreetingInstruction::RemoveFromAllowlist { .. } => 30,
+            GreetingInstruction::SetAllowlistEnabled { .. } => 31,
+            GreetingInstruction::SetMinUpdateInterval { .. } => 32,
+            GreetingInstruction::FreezeGreeting => 33,
+            GreetingInstruction::CloseGreetingToRentPayer => 34,
+            GreetingInstruction::SweepSurplus => 35,
+        }
+    }
+
+    // One instance of every variant, in declaration order.
+    pub(crate) fn one_of_each() -> Vec<GreetingInstruction> {
+        vec![
+            GreetingInstruction::CreateGreeting {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+                index: 0,
+            },
+            GreetingInstruction::SetGreeting {
+                message: "Hi".to_string(),
+                name: Some("Bob".to_string()),
+            },
+            GreetingInstruction::CloseGreetingAccount,
+            GreetingInstruction::TransferAuthority {
+                new_authority: Pubkey::new_unique(),
+            },
+            GreetingInstruction::ResetGreeting,
+            GreetingInstruction::MigrateGreeting,
+            GreetingInstruction::GrowGreeting { new_size: 1_000 },
+            GreetingInstruction::AppendToMessage {
+                suffix: "!".to_string(),
+            },
+            GreetingInstruction::LikeGreeting,
+            GreetingInstruction::InitializeConfig {
+                default_message: "Hi".to_string(),
+            },
+            GreetingInstruction::UpdateConfig {
+                default_message: "Hi".to_string(),
+            },
+            GreetingInstruction::SetPaused { paused: true },
+            GreetingInstruction::RenameGreeting {
+                name: "Bob".to_string(),
+            },
+            GreetingInstruction::CreateGreetingWithBump {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+                index: 0,
+                bump: 255,
+            },
+            GreetingInstruction::SetVisibility { is_public: false },
+            GreetingInstruction::CreateNamedGreeting {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+            },
+            GreetingInstruction::BatchSetGreeting {
+                messages: vec!["Hi".to_string(), "Hey".to_string()],
+            },
+            GreetingInstruction::SetOrCreateGreeting {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+            },
+            GreetingInstruction::LogGreetingInfo,
+            GreetingInstruction::SetCoAuthority {
+                co_authority: Some(Pubkey::new_unique()),
+            },
+            GreetingInstruction::SetGreetingChecked {
+                message: "Hi".to_string(),
+                expected_update_count: 0,
+            },
+            GreetingInstruction::CreateGreetingWithExpiry {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+                index: 0,
+                expires_at: 1_700_000_000,
+            },
+            GreetingInstruction::InitializeStats,
+            GreetingInstruction::DonateToGreeting { amount: 1_000 },
+            GreetingInstruction::WithdrawDonations { amount: 1_000 },
+            GreetingInstruction::CreateGreetingWithPdaAuthority {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+                index: 0,
+            },
+            GreetingInstruction::SetGreetingIfChanged {
+                message: "Hi".to_string(),
+            },
+            GreetingInstruction::RepairBump {
+                creator: Pubkey::new_unique(),
+                index: Some(0),
+                name: None,
+            },
+            GreetingInstruction::CreateCompactGreeting {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+                index: 0,
+            },
+            GreetingInstruction::AddToAllowlist {
+                key: Pubkey::new_unique(),
+            },
+            GreetingInstruction::RemoveFromAllowlist {
+                key: Pubkey::new_unique(),
+            },
+            GreetingInstruction::SetAllowlistEnabled { enabled: true },
+            GreetingInstruction::SetMinUpdateInterval { secs: 60 },
+            GreetingInstruction::FreezeGreeting,
+            GreetingInstruction::CloseGreetingToRentPayer,
+            GreetingInstruction::SweepSurplus,
+        ]
+    }
+
+    #[test]
+    fn test_pack_round_trips_every_variant() {
+        for instruction in one_of_each() {
+            let bytes = instruction.pack();
+            assert_eq!(bytes, borsh::to_vec(&instruction).unwrap());
+            assert_eq!(GreetingInstruction::unpack(&bytes).unwrap(), instruction);
+        }
+    }
+
+    #[test]
+    fn test_unpack_rejects_trailing_bytes() {
+        let mut bytes = GreetingInstruction::LikeGreeting.pack();
+        bytes.push(0);
+        assert_eq!(
+            GreetingInstruction::unpack(&bytes),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_instruction_discriminants_are_stable() {
+        let instructions = one_of_each();
+
+        for (position, instruction) in instructions.iter().enumerate() {
+            let bytes = borsh::to_vec(instruction).unwrap();
+            assert_eq!(
+                bytes[0],
+                expected_discriminant(instruction),
+                "{:?}",
+                instruction
+            );
+            // One sample per variant, in declaration order
+            assert_eq!(bytes[0] as usize, position);
+        }
+    }
+
+    // SetGreeting gained `name` after clients were deployed. Their encoding, pinned here
+    // byte for byte, must keep decoding, as `name: None`.
+    #[test]
+    fn test_set_greeting_without_name_still_decodes() {
+        let legacy = [1, 2, 0, 0, 0, b'H', b'i'];
+        let message_only = GreetingInstruction::SetGreeting {
+            message: "Hi".to_string(),
+            name: None,
+        };
+        assert_eq!(
+            GreetingInstruction::unpack(&legacy),
+            Ok(message_only.clone())
+        );
+        assert_eq!(message_only.pack(), [&legacy[..], &[0]].concat());
+
+        let renamed = GreetingInstruction::SetGreeting {
+            message: "Hi".to_string(),
+            name: Some("Bo".to_string()),
+        };
+        assert_eq!(
+            renamed.pack(),
+            [&legacy[..], &[1, 2, 0, 0, 0, b'B', b'o']].concat()
+        );
+        assert_eq!(GreetingInstruction::unpack(&renamed.pack()), Ok(renamed));
+
+        // Neither encoding may carry trailing bytes
+        let mut trailing = legacy.to_vec();
+        trailing.extend_from_slice(&[1, 0xff]);
+        assert_eq!(
+            GreetingInstruction::unpack(&trailing),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
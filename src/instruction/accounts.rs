@@ -0,0 +1,596 @@
+//! Typed account lists for the instructions, each turned into the `AccountMeta`s the
+//! program expects by `to_account_metas`, in order and with the signer and writable
+//! flags set. The order is written down once here: the builders assemble their
+//! instructions from these, so do the `cpi` helpers, and a client passing accounts the
+//! builders don't derive, say a greeting at an address it looked up, can fill one in by
+//! hand. Unlike the builders, they don't need the `client` feature.
+//!
+//! Instructions that share an account list share a struct; each struct names the
+//! instructions it is for. Lists for instructions the pause flag applies to end with
+//! the config PDA, see `GreetingInstruction`. Optional accounts a few instructions
+//! accept, like the stats PDA, are left to `with_stats`.
+
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey, system_program};
+
+use crate::state::{derive_config_pda, derive_registry_pda, derive_stats_pda};
+
+/// Accounts for `CreateGreeting`, `CreateGreetingWithBump`, `CreateCompactGreeting`,
+/// `CreateGreetingWithExpiry`, `CreateNamedGreeting` and `SetOrCreateGreeting`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateGreetingAccounts {
+    /// Pays for the new accounts and becomes the greeting's authority. Signs; writable.
+    pub payer: Pubkey,
+    /// The greeting PDA, indexed or named to match the instruction; writable.
+    pub greeting_pda: Pubkey,
+    pub system_program: Pubkey,
+    /// `derive_registry_pda(program_id, payer)`; writable.
+    pub registry: Pubkey,
+    /// Only for `SetOrCreateGreeting` updating a greeting that has a co-authority, which
+    /// must co-sign. Signs.
+    pub co_authority: Option<Pubkey>,
+    pub config: Pubkey,
+}
+
+impl CreateGreetingAccounts {
+    /// The accounts for `payer` creating `greeting_pda`, deriving the rest.
+    pub fn new(program_id: &Pubkey, payer: &Pubkey, greeting_pda: &Pubkey) -> Self {
+        Self {
+            payer: *payer,
+            greeting_pda: *greeting_pda,
+            system_program: system_program::ID,
+            registry: derive_registry_pda(program_id, payer).0,
+            co_authority: None,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.greeting_pda, false),
+            AccountMeta::new_readonly(self.system_program, false),
+            AccountMeta::new(self.registry, false),
+        ];
+        if let Some(co_authority) = self.co_authority {
+            metas.push(AccountMeta::new_readonly(co_authority, true));
+        }
+        metas.push(AccountMeta::new_readonly(self.config, false));
+        metas
+    }
+}
+
+/// Accounts for `CreateGreetingWithPdaAuthority`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateGreetingWithPdaAuthorityAccounts {
+    /// Pays for the new accounts. Signs; writable.
+    pub payer: Pubkey,
+    /// Another program's PDA, which becomes the greeting's authority. Signs.
+    pub authority: Pubkey,
+    /// `derive_greeting_pda(program_id, authority, index)`; writable.
+    pub greeting_pda: Pubkey,
+    pub system_program: Pubkey,
+    /// `derive_registry_pda(program_id, authority)`; writable.
+    pub registry: Pubkey,
+    pub config: Pubkey,
+}
+
+impl CreateGreetingWithPdaAuthorityAccounts {
+    /// The accounts for `payer` funding `greeting_pda` for `authority`, deriving the rest.
+    pub fn new(
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+    ) -> Self {
+        Self {
+            payer: *payer,
+            authority: *authority,
+            greeting_pda: *greeting_pda,
+            system_program: system_program::ID,
+            registry: derive_registry_pda(program_id, authority).0,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new_readonly(self.authority, true),
+            AccountMeta::new(self.greeting_pda, false),
+            AccountMeta::new_readonly(self.system_program, false),
+            AccountMeta::new(self.registry, false),
+            AccountMeta::new_readonly(self.config, false),
+        ]
+    }
+}
+
+/// Accounts for the instructions an authority signs to change its greeting:
+/// `SetGreeting`, `SetGreetingChecked`, `SetGreetingIfChanged`, `SetCoAuthority`,
+/// `ResetGreeting`, `TransferAuthority`, `AppendToMessage`, `RenameGreeting`,
+/// `SetVisibility`, `RepairBump` and `FreezeGreeting`. Also `LikeGreeting`, with the
+/// liker as `authority`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreetingAuthorityAccounts {
+    /// The greeting's authority, or the liker for `LikeGreeting`. Signs.
+    pub authority: Pubkey,
+    /// The greeting; writable.
+    pub greeting_pda: Pubkey,
+    /// The greeting's co-authority, if it has one, for the instructions it must co-sign:
+    /// the `SetGreeting` family, `ResetGreeting`, `AppendToMessage`, `RenameGreeting`,
+    /// `SetCoAuthority`, `FreezeGreeting`, `TransferAuthority` and `SetVisibility`. Signs.
+    pub co_authority: Option<Pubkey>,
+    pub config: Pubkey,
+}
+
+impl GreetingAuthorityAccounts {
+    /// The accounts for `authority` changing `greeting_pda`, with no co-authority.
+    pub fn new(program_id: &Pubkey, authority: &Pubkey, greeting_pda: &Pubkey) -> Self {
+        Self {
+            authority: *authority,
+            greeting_pda: *greeting_pda,
+            co_authority: None,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new_readonly(self.authority, true),
+            AccountMeta::new(self.greeting_pda, false),
+        ];
+        if let Some(co_authority) = self.co_authority {
+            metas.push(AccountMeta::new_readonly(co_authority, true));
+        }
+        metas.push(AccountMeta::new_readonly(self.config, false));
+        metas
+    }
+}
+
+/// Accounts for `BatchSetGreeting`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchSetGreetingAccounts {
+    /// The authority of every greeting. Signs.
+    pub authority: Pubkey,
+    /// The greetings, in the order of the instruction's messages; writable.
+    pub greeting_pdas: Vec<Pubkey>,
+    /// The co-authority of each greeting that has one, in the greetings' order. Sign.
+    pub co_authorities: Vec<Pubkey>,
+    pub config: Pubkey,
+}
+
+impl BatchSetGreetingAccounts {
+    pub fn new(program_id: &Pubkey, authority: &Pubkey, greeting_pdas: Vec<Pubkey>) -> Self {
+        Self {
+            authority: *authority,
+            greeting_pdas,
+            co_authorities: Vec::new(),
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut metas = vec![AccountMeta::new_readonly(self.authority, true)];
+        metas.extend(
+            self.greeting_pdas
+                .iter()
+                .map(|greeting_pda| AccountMeta::new(*greeting_pda, false)),
+        );
+        metas.extend(
+            self.co_authorities
+                .iter()
+                .map(|co_authority| AccountMeta::new_readonly(*co_authority, true)),
+        );
+        metas.push(AccountMeta::new_readonly(self.config, false));
+        metas
+    }
+}
+
+/// Accounts for `LogGreetingInfo`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogGreetingInfoAccounts {
+    pub greeting_pda: Pubkey,
+}
+
+impl LogGreetingInfoAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![AccountMeta::new_readonly(self.greeting_pda, false)]
+    }
+}
+
+/// Accounts for `CloseGreetingAccount` and `CloseGreetingToRentPayer`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CloseGreetingAccounts {
+    /// The greeting's authority, or anyone once the greeting has expired.
+    pub closer: Pubkey,
+    /// Whether `closer` signs, as the authority must. Closing an expired greeting
+    /// needs no signature.
+    pub closer_signs: bool,
+    /// The greeting; writable.
+    pub greeting_pda: Pubkey,
+    /// Receives the greeting's lamports: anything for the authority, the authority
+    /// itself for an expired greeting, and always the greeting's `rent_payer` for
+    /// `CloseGreetingToRentPayer`. Writable.
+    pub destination: Pubkey,
+    /// The registry of the greeting's creator; writable. `new` and `expired` assume the
+    /// authority created the greeting; for one received through TransferAuthority, set
+    /// this to the creator's registry.
+    pub registry: Pubkey,
+    /// The greeting's co-authority, if it has one and hasn't expired, which must co-sign.
+    pub co_authority: Option<Pubkey>,
+    pub config: Pubkey,
+}
+
+impl CloseGreetingAccounts {
+    /// The accounts for `authority` closing its `greeting_pda` into `destination`.
+    pub fn new(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        destination: &Pubkey,
+    ) -> Self {
+        Self {
+            closer: *authority,
+            closer_signs: true,
+            greeting_pda: *greeting_pda,
+            destination: *destination,
+            registry: derive_registry_pda(program_id, authority).0,
+            co_authority: None,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    /// The accounts for `closer` closing the expired `greeting_pda`, refunding its `authority`.
+    pub fn expired(
+        program_id: &Pubkey,
+        closer: &Pubkey,
+        greeting_pda: &Pubkey,
+        authority: &Pubkey,
+    ) -> Self {
+        Self {
+            closer: *closer,
+            closer_signs: false,
+            ..Self::new(program_id, authority, greeting_pda, authority)
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new_readonly(self.closer, self.closer_signs),
+            AccountMeta::new(self.greeting_pda, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new(self.registry, false),
+        ];
+        if let Some(co_authority) = self.co_authority {
+            metas.push(AccountMeta::new_readonly(co_authority, true));
+        }
+        metas.push(AccountMeta::new_readonly(self.config, false));
+        metas
+    }
+}
+
+/// Accounts for `WithdrawDonations` and `SweepSurplus`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WithdrawDonationsAccounts {
+    /// The greeting's authority. Signs.
+    pub authority: Pubkey,
+    /// The greeting; writable.
+    pub greeting_pda: Pubkey,
+    /// Receives the lamports; writable.
+    pub destination: Pubkey,
+    pub config: Pubkey,
+}
+
+impl WithdrawDonationsAccounts {
+    pub fn new(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        destination: &Pubkey,
+    ) -> Self {
+        Self {
+            authority: *authority,
+            greeting_pda: *greeting_pda,
+            destination: *destination,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(self.authority, true),
+            AccountMeta::new(self.greeting_pda, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new_readonly(self.config, false),
+        ]
+    }
+}
+
+/// Accounts for `GrowGreeting`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrowGreetingAccounts {
+    /// The greeting's authority. Signs.
+    pub authority: Pubkey,
+    /// The greeting; writable.
+    pub greeting_pda: Pubkey,
+    /// Covers the extra rent. Signs; writable.
+    pub payer: Pubkey,
+    pub system_program: Pubkey,
+    pub config: Pubkey,
+}
+
+impl GrowGreetingAccounts {
+    pub fn new(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        greeting_pda: &Pubkey,
+        payer: &Pubkey,
+    ) -> Self {
+        Self {
+            authority: *authority,
+            greeting_pda: *greeting_pda,
+            payer: *payer,
+            system_program: system_program::ID,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(self.authority, true),
+            AccountMeta::new(self.greeting_pda, false),
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new_readonly(self.system_program, false),
+            AccountMeta::new_readonly(self.config, false),
+        ]
+    }
+}
+
+/// Accounts for the instructions that move lamports into a greeting: `MigrateGreeting`,
+/// funded by the greeting's authority, and `DonateToGreeting`, by the donor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FundGreetingAccounts {
+    /// The authority migrating, or the donor. Signs; writable.
+    pub funder: Pubkey,
+    /// The greeting; writable.
+    pub greeting_pda: Pubkey,
+    pub system_program: Pubkey,
+    pub config: Pubkey,
+}
+
+impl FundGreetingAccounts {
+    pub fn new(program_id: &Pubkey, funder: &Pubkey, greeting_pda: &Pubkey) -> Self {
+        Self {
+            funder: *funder,
+            greeting_pda: *greeting_pda,
+            system_program: system_program::ID,
+            config: derive_config_pda(program_id).0,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.funder, true),
+            AccountMeta::new(self.greeting_pda, false),
+            AccountMeta::new_readonly(self.system_program, false),
+            AccountMeta::new_readonly(self.config, false),
+        ]
+    }
+}
+
+/// Accounts for the config instructions. `UpdateConfig`, `SetPaused` and
+/// `RemoveFromAllowlist` take just the admin and the config; `InitializeConfig`,
+/// `AddToAllowlist`, `SetAllowlistEnabled` and `SetMinUpdateInterval` may create or
+/// grow the config, so they also take the System Program, with the admin paying.
+/// The pause flag doesn't apply to any of them, so no trailing config is added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigAccounts {
+    /// The config admin, or for `InitializeConfig` the payer who becomes it. Signs;
+    /// writable when it pays.
+    pub admin: Pubkey,
+    /// `derive_config_pda(program_id)`; writable.
+    pub config: Pubkey,
+    /// The System Program, for the instructions that may create or grow the config.
+    pub system_program: Option<Pubkey>,
+}
+
+impl ConfigAccounts {
+    /// The accounts for `admin` changing the config in place.
+    pub fn new(program_id: &Pubkey, admin: &Pubkey) -> Self {
+        Self {
+            admin: *admin,
+            config: derive_config_pda(program_id).0,
+            system_program: None,
+        }
+    }
+
+    /// The accounts for `admin` changing the config, paying to create or grow it.
+    pub fn paid(program_id: &Pubkey, admin: &Pubkey) -> Self {
+        Self {
+            system_program: Some(system_program::ID),
+            ..Self::new(program_id, admin)
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let pays = self.system_program.is_some();
+        let mut metas = vec![
+            AccountMeta {
+                pubkey: self.admin,
+                is_signer: true,
+                is_writable: pays,
+            },
+            AccountMeta::new(self.config, false),
+        ];
+        if let Some(system_program) = self.system_program {
+            metas.push(AccountMeta::new_readonly(system_program, false));
+        }
+        metas
+    }
+}
+
+/// Accounts for `InitializeStats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitializeStatsAccounts {
+    /// Pays for the stats account. Signs; writable.
+    pub payer: Pubkey,
+    /// `derive_stats_pda(program_id)`; writable.
+    pub stats: Pubkey,
+    pub system_program: Pubkey,
+}
+
+impl InitializeStatsAccounts {
+    pub fn new(program_id: &Pubkey, payer: &Pubkey) -> Self {
+        Self {
+            payer: *payer,
+            stats: derive_stats_pda(program_id).0,
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.stats, false),
+            AccountMeta::new_readonly(self.system_program, false),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::derive_greeting_pda;
+
+    #[test]
+    fn test_create_greeting_metas_order_and_flags() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let greeting_pda = derive_greeting_pda(&program_id, &payer, 0).0;
+
+        assert_eq!(
+            CreateGreetingAccounts::new(&program_id, &payer, &greeting_pda).to_account_metas(),
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(greeting_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_registry_pda(&program_id, &payer).0, false),
+                AccountMeta::new_readonly(derive_config_pda(&program_id).0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optional_accounts_go_before_the_config() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let co_authority = Pubkey::new_unique();
+        let greeting_pda = Pubkey::new_unique();
+        let config = derive_config_pda(&program_id).0;
+
+        let mut accounts = GreetingAuthorityAccounts::new(&program_id, &authority, &greeting_pda);
+        assert_eq!(
+            accounts.to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(greeting_pda, false),
+                AccountMeta::new_readonly(config, false),
+            ]
+        );
+        accounts.co_authority = Some(co_authority);
+        assert_eq!(
+            accounts.to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(greeting_pda, false),
+                AccountMeta::new_readonly(co_authority, true),
+                AccountMeta::new_readonly(config, false),
+            ]
+        );
+
+        let greeting_pdas = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        assert_eq!(
+            BatchSetGreetingAccounts::new(&program_id, &authority, greeting_pdas.clone())
+                .to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(greeting_pdas[0], false),
+                AccountMeta::new(greeting_pdas[1], false),
+                AccountMeta::new_readonly(config, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_close_metas_sign_only_for_the_authority() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let closer = Pubkey::new_unique();
+        let greeting_pda = Pubkey::new_unique();
+        let registry = derive_registry_pda(&program_id, &authority).0;
+        let config = derive_config_pda(&program_id).0;
+
+        let destination = Pubkey::new_unique();
+        assert_eq!(
+            CloseGreetingAccounts::new(&program_id, &authority, &greeting_pda, &destination)
+                .to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(greeting_pda, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new(registry, false),
+                AccountMeta::new_readonly(config, false),
+            ]
+        );
+        let co_authority = Pubkey::new_unique();
+        assert_eq!(
+            CloseGreetingAccounts {
+                co_authority: Some(co_authority),
+                ..CloseGreetingAccounts::new(&program_id, &authority, &greeting_pda, &destination)
+            }
+            .to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(greeting_pda, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new(registry, false),
+                AccountMeta::new_readonly(co_authority, true),
+                AccountMeta::new_readonly(config, false),
+            ]
+        );
+        // The rent goes back to the authority, whose registry is still the one passed
+        assert_eq!(
+            CloseGreetingAccounts::expired(&program_id, &closer, &greeting_pda, &authority)
+                .to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(closer, false),
+                AccountMeta::new(greeting_pda, false),
+                AccountMeta::new(authority, false),
+                AccountMeta::new(registry, false),
+                AccountMeta::new_readonly(config, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_admin_is_writable_only_when_paying() {
+        let program_id = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let config = derive_config_pda(&program_id).0;
+
+        assert_eq!(
+            ConfigAccounts::new(&program_id, &admin).to_account_metas(),
+            vec![
+                AccountMeta::new_readonly(admin, true),
+                AccountMeta::new(config, false),
+            ]
+        );
+        assert_eq!(
+            ConfigAccounts::paid(&program_id, &admin).to_account_metas(),
+            vec![
+                AccountMeta::new(admin, true),
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ]
+        );
+    }
+}
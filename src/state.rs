@@ -0,0 +1,1641 @@
+//! Account layouts owned by the program, their PDAs, and the helpers that encode
+//! and decode them.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solana_system_interface::MAX_PERMITTED_DATA_LENGTH;
+
+/// Structure of the data stored in a greeting account.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct GreetingAccountState {
+    // Layout version of this account, see `CURRENT_VERSION`. Kept first so it can be
+    // read before the rest of the layout is known.
+    pub version: u8,
+
+    // The Authority (public key) that is allowed to change the greeting.
+    pub authority: Pubkey,
+
+    // A name or title for the greeting.
+    pub name: String,
+
+    // The greeting message itself.
+    pub message: String,
+
+    // A counter for how many times the greeting has been updated (just for fun!).
+    // Counters saturate: at u32::MAX they stay there and the update still succeeds,
+    // rather than wrapping back to zero or failing the edit.
+    pub update_count: u32,
+
+    // The canonical bump of the greeting PDA, stored so instructions that sign as the
+    // PDA don't have to search for it again with `find_program_address`.
+    // Appended last on purpose: accounts created before this field existed decode with
+    // `bump == 0` from the zero padding left by `get_max_space_needed()`, and must not be
+    // trusted to sign until their bump has been rewritten.
+    pub bump: u8,
+
+    // Unix timestamps (from the Clock sysvar) of creation and of the latest message change.
+    pub created_at: i64,
+    pub updated_at: i64,
+
+    // Set once by CreateGreeting. Zeroed or foreign data decodes with this false.
+    pub is_initialized: bool,
+
+    // How many times anyone has liked the greeting. Tracked separately from `update_count`
+    // so popularity doesn't get mixed up with edits. Saturates like `update_count`.
+    pub likes: u32,
+
+    // Whether off-chain tooling should show the greeting. Private greetings can't be liked.
+    // Accounts from before this field existed were all public, so `unpack_greeting`
+    // sets it to true rather than leaving the zero-padded false.
+    pub is_public: bool,
+
+    // A second key that must co-sign SetGreeting (and changes to itself) when set, for
+    // greetings shared two-of-two. Borsh stores `None` as a single zero byte, so older
+    // accounts zero-padded by `unpack_greeting` decode with no co-authority.
+    pub co_authority: Option<Pubkey>,
+
+    // Unix timestamp after which the greeting can't be edited and anyone may close it,
+    // returning the rent to the authority. `None`, also what migrated accounts get,
+    // means it never expires.
+    pub expires_at: Option<i64>,
+
+    // Lamports sent through DonateToGreeting and not yet taken out with WithdrawDonations.
+    // Lamports sent by plain transfer aren't counted here but can still be withdrawn, so
+    // withdrawals bring this down to zero at most.
+    pub total_donations: u64,
+
+    // The messages replaced by the last `HISTORY_LEN` edits, as a ring buffer:
+    // `history_next` is the slot the next replaced message goes in, which once the buffer
+    // is full also holds the oldest entry. Unused slots are empty strings, which no message
+    // can be. Read them in order with `history()`.
+    pub history: [String; GreetingAccountState::HISTORY_LEN],
+    pub history_next: u8,
+
+    // Set once by FreezeGreeting and never cleared. A frozen greeting can still be read,
+    // but every instruction that would change or close it fails with GreetingFrozen.
+    pub frozen: bool,
+
+    // The account that paid the greeting's rent at creation, which CloseGreetingToRentPayer
+    // refunds. Greetings from before this field decode with the default Pubkey, meaning
+    // unknown, and can only be closed with CloseGreetingAccount.
+    pub rent_payer: Pubkey,
+
+    // The authority the greeting was created under, whose registry it counts against.
+    // Unlike `authority`, TransferAuthority never changes it, so closing the greeting frees
+    // the slot it was charged to. Greetings from before this field decode with the default
+    // Pubkey, meaning unknown, and count against whoever is authority when they close.
+    pub creator: Pubkey,
+}
+
+// Let's define some constraints, especially for Strings.
+// Solana accounts have size limits. Unbounded strings are risky.
+impl GreetingAccountState {
+    // Max length for the 'name' field.
+    pub const MAX_NAME_LENGTH: usize = 32;
+    // Max length for the 'message' field, in characters (Unicode scalar values) rather
+    // than bytes, so a message in emoji or CJK gets the same allowance as one in ASCII.
+    // With the `graphemes` feature it counts grapheme clusters instead, and only messages
+    // that also fit in `MAX_MESSAGE_LENGTH` bytes are accepted.
+    pub const MAX_MESSAGE_CHARS: usize = 128;
+    // Bytes reserved for the 'message' field: the UTF-8 worst case of 4 bytes per
+    // character. The tradeoff is rent: every account pays for the full reservation,
+    // even though an ASCII message uses only a quarter of it.
+    pub const MAX_MESSAGE_LENGTH: usize = 4 * Self::MAX_MESSAGE_CHARS;
+    // How many earlier messages `history` keeps. Each slot reserves a full message's worth
+    // of space, so this multiplies the rent of every account.
+    pub const HISTORY_LEN: usize = 3;
+    // Message restored by `ResetGreeting`.
+    pub const DEFAULT_MESSAGE: &'static str = "Hello, World!";
+    // Placeholder `rendered_message` replaces with the name.
+    pub const NAME_PLACEHOLDER: &'static str = "{name}";
+    // Discriminator for account type, written as an 8-byte prefix ahead of the Borsh data
+    // so arbitrary program-owned accounts can't be mistaken for greetings.
+    pub const ACCOUNT_DISCRIMINATOR: &'static str = "GREETING";
+    // Layout version written by this build. Bump it whenever fields are added. New fields
+    // are only ever appended and must decode from zero bytes as their initial value, which
+    // is what lets `unpack_greeting` upgrade any older version by zero-padding it. A field
+    // whose initial value isn't zero needs a fix-up there, like `is_public`.
+    // Version 4 added no fields but reserved room for `MAX_MESSAGE_CHARS`; bumping it makes
+    // MigrateGreeting reallocate older, smaller accounts.
+    pub const CURRENT_VERSION: u8 = 11;
+
+    // First layout version with `is_public`.
+    pub const VISIBILITY_VERSION: u8 = 3;
+    // Calculate the maximum space needed for the account space.
+    pub const fn get_max_space_needed() -> usize {
+        // 8 bytes for the account discriminator prefix
+        // u8 = 1 byte for version
+        // Pubkey = 32 bytes
+        // String length (u32 = 4 bytes) + max characters for name
+        // String length (u32 = 4 bytes) + max bytes for message
+        // u32 = 4 bytes for update_count
+        // u8 = 1 byte for bump
+        // i64 = 8 bytes each for created_at and updated_at
+        // bool = 1 byte for is_initialized
+        // u32 = 4 bytes for likes
+        // bool = 1 byte for is_public
+        // Option<Pubkey> = 1 byte tag + 32 bytes, reserved in full even when None
+        // Option<i64> = 1 byte tag + 8 bytes for expires_at
+        // u64 = 8 bytes for total_donations
+        // HISTORY_LEN strings, each reserved like the message
+        // u8 = 1 byte for history_next
+        // bool = 1 byte for frozen
+        // Pubkey = 32 bytes for rent_payer
+        // Pubkey = 32 bytes for creator
+
+        checked_sum(&[
+            Self::ACCOUNT_DISCRIMINATOR.len(),                  // discriminator
+            1,                                                  // version
+            32,                                                 // authority
+            4 + Self::MAX_NAME_LENGTH,                          // name
+            4 + Self::MAX_MESSAGE_LENGTH,                       // message
+            4,                                                  // update_count
+            1,                                                  // bump
+            8,                                                  // created_at
+            8,                                                  // updated_at
+            1,                                                  // is_initialized
+            4,                                                  // likes
+            1,                                                  // is_public
+            1 + 32,                                             // co_authority
+            1 + 8,                                              // expires_at
+            8,                                                  // total_donations
+            Self::HISTORY_LEN * (4 + Self::MAX_MESSAGE_LENGTH), // history
+            1,                                                  // history_next
+            1,                                                  // frozen
+            32,                                                 // rent_payer
+            32,                                                 // creator
+        ])
+    }
+
+    /// Space for a new greeting holding `name` and `message`, sized to its serialized
+    /// state rather than the largest possible one, as allocated by CreateCompactGreeting.
+    ///
+    /// Rent is charged on the data plus 128 bytes of fixed account overhead, so a short
+    /// ASCII greeting pays about an eighth of the rent of a `get_max_space_needed` account.
+    /// The catch is that nothing is left over: every edit also copies the old message
+    /// into `history`, so even a message of the same length fails with
+    /// `AccountDataTooSmall` until GrowGreeting reallocates the account.
+    ///
+    /// Never returns `GreetingAccountStateV0::SPACE`, the size `greeting_layout_version` reads
+    /// as the unversioned layout; a greeting that would land on it gets one spare byte instead.
+    pub fn exact_space_needed(name: &str, message: &str) -> usize {
+        let space = checked_sum(&[
+            Self::ACCOUNT_DISCRIMINATOR.len(), // discriminator
+            1,                                 // version
+            32,                                // authority
+            4 + name.len(),                    // name
+            4 + message.len(),                 // message
+            4,                                 // update_count
+            1,                                 // bump
+            8,                                 // created_at
+            8,                                 // updated_at
+            1,                                 // is_initialized
+            4,                                 // likes
+            1,                                 // is_public
+            1,                                 // co_authority, None
+            1,                                 // expires_at, None
+            8,                                 // total_donations
+            Self::HISTORY_LEN * 4,             // history, all empty
+            1,                                 // history_next
+            1,                                 // frozen
+            32,                                // rent_payer
+            32,                                // creator
+        ]);
+        if space == GreetingAccountStateV0::SPACE {
+            space + 1
+        } else {
+            space
+        }
+    }
+
+    /// Whether the greeting has expired at unix time `now`, normally `Clock::unix_timestamp`.
+    /// A greeting expires at its `expires_at` second, not after it.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// Messages this greeting held before, oldest first. Only the last `HISTORY_LEN` are
+    /// kept; older ones are dropped as new edits come in.
+    pub fn history(&self) -> Vec<&str> {
+        let next = usize::from(self.history_next) % Self::HISTORY_LEN;
+        (0..Self::HISTORY_LEN)
+            .map(|offset| self.history[(next + offset) % Self::HISTORY_LEN].as_str())
+            .filter(|message| !message.is_empty())
+            .collect()
+    }
+
+    // Replaces the message, moving the old one into the history, and records the edit
+    // at `now`.
+    pub(crate) fn set_message(&mut self, message: String, now: i64) {
+        let previous = std::mem::replace(&mut self.message, message);
+        self.push_history(previous);
+        self.update_count = self.update_count.saturating_add(1);
+        self.updated_at = now;
+    }
+
+    // Records `message` as the newest history entry, evicting the oldest if all slots are
+    // taken. Empty messages are skipped, since an empty slot reads as unused.
+    pub(crate) fn push_history(&mut self, message: String) {
+        if message.is_empty() {
+            return;
+        }
+        let next = usize::from(self.history_next) % Self::HISTORY_LEN;
+        self.history[next] = message;
+        self.history_next = ((next + 1) % Self::HISTORY_LEN) as u8;
+    }
+
+    /// The message with every `{name}` replaced by the greeting's name, so a message stored
+    /// as the template `Hello {name}!` reads `Hello Alice!`. `{name}` is the only
+    /// placeholder; any other braces are left as they are. The result is at most
+    /// `MAX_NAME_LENGTH` bytes longer per placeholder, and a name that itself contains
+    /// `{name}` isn't expanded again. The program stores and checks `message` as written,
+    /// so render only when displaying it.
+    pub fn rendered_message(&self) -> String {
+        self.message.replace(Self::NAME_PLACEHOLDER, &self.name)
+    }
+
+    /// One-line description for logs and UIs, e.g. `Alice: Hello (edited 2x by 7Xd3..Fq9T)`.
+    /// The authority is cut to its first and last four base58 characters.
+    pub fn summary(&self) -> String {
+        // Base58 is ASCII, so these byte offsets are character boundaries
+        let authority = self.authority.to_string();
+        format!(
+            "{}: {} (edited {}x by {}..{})",
+            self.name,
+            self.message,
+            self.update_count,
+            &authority[..4],
+            &authority[authority.len() - 4..]
+        )
+    }
+}
+
+/// Layout of greeting accounts written before the `version` field existed.
+/// Only `unpack_greeting` reads it.
+#[derive(BorshDeserialize, Debug)]
+pub(crate) struct GreetingAccountStateV0 {
+    authority: Pubkey,
+    name: String,
+    message: String,
+    update_count: u32,
+    bump: u8,
+    created_at: i64,
+    updated_at: i64,
+    is_initialized: bool,
+}
+
+impl GreetingAccountStateV0 {
+    // Every V0 account was allocated at exactly this size. Versioned accounts never are,
+    // which is what tells the layouts apart without a version byte: older ones are larger,
+    // and `exact_space_needed` steps around it for compact ones.
+    pub(crate) const SPACE: usize = 230;
+
+    pub(crate) fn into_current(self) -> GreetingAccountState {
+        GreetingAccountState {
+            version: GreetingAccountState::CURRENT_VERSION,
+            authority: self.authority,
+            name: self.name,
+            message: self.message,
+            update_count: self.update_count,
+            bump: self.bump,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            is_initialized: self.is_initialized,
+            is_public: true,
+            ..GreetingAccountState::default()
+        }
+    }
+}
+
+/// Structure of the data stored in the program's single config account.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct ConfigState {
+    // The only key allowed to change the config.
+    pub admin: Pubkey,
+
+    // Message ResetGreeting restores.
+    pub default_message: String,
+
+    // Kill-switch: while set, only config instructions are accepted.
+    pub paused: bool,
+
+    // While set, only keys in `allowlist` may own new greetings. Off by default, and in
+    // configs created before the allowlist existed.
+    pub allowlist_enabled: bool,
+
+    // Keys allowed to create greetings while `allowlist_enabled` is set, at most
+    // `MAX_ALLOWLIST_LEN` of them. Kept while the gate is off, ready for turning it on.
+    pub allowlist: Vec<Pubkey>,
+
+    // Least time, in seconds, between two SetGreeting edits of one greeting. Zero, the
+    // default and what configs from before it existed get, turns the limit off.
+    pub min_update_interval_secs: u32,
+}
+
+impl ConfigState {
+    // Tags config account data, distinct from greeting accounts.
+    pub const ACCOUNT_DISCRIMINATOR: &'static str = "GRCONFIG";
+
+    // Most keys the allowlist holds. Every config reserves room for all of them.
+    pub const MAX_ALLOWLIST_LEN: usize = 16;
+
+    // Calculate the maximum space needed for the config account.
+    pub const fn get_max_space_needed() -> usize {
+        checked_sum(&[
+            Self::ACCOUNT_DISCRIMINATOR.len(),            // discriminator
+            32,                                           // admin
+            4 + GreetingAccountState::MAX_MESSAGE_LENGTH, // default_message
+            1,                                            // paused
+            1,                                            // allowlist_enabled
+            4 + Self::MAX_ALLOWLIST_LEN * 32,             // allowlist
+            4,                                            // min_update_interval_secs
+        ])
+    }
+
+    // Sizes of configs created by earlier builds: before the allowlist was added, then
+    // before `min_update_interval_secs` was. Both decode zero-padded. AddToAllowlist,
+    // SetAllowlistEnabled and SetMinUpdateInterval grow them to the current size; until
+    // then, other config writes fail with AccountDataTooSmall if the account has no room
+    // left for the new fields.
+    pub(crate) const LEGACY_SPACES: [usize; 2] = [557, 1074];
+}
+
+/// Seed for the config account PDA.
+pub const CONFIG_SEED: &[u8] = b"config";
+
+/// Derives the program's config PDA. Seeds are `[CONFIG_SEED]`.
+pub fn derive_config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+/// Decodes config state from raw account data, after checking its discriminator.
+/// Configs from before the allowlist existed decode with the gate off and no keys, and
+/// those from before the update interval with no limit.
+pub fn unpack_config(data: &[u8]) -> Result<ConfigState, ProgramError> {
+    if ConfigState::LEGACY_SPACES.contains(&data.len()) {
+        let mut padded = data.to_vec();
+        padded.resize(ConfigState::get_max_space_needed(), 0);
+        return unpack_tagged(ConfigState::ACCOUNT_DISCRIMINATOR, &padded);
+    }
+    unpack_tagged(ConfigState::ACCOUNT_DISCRIMINATOR, data)
+}
+
+/// Most greetings one authority may hold open at a time, enforced through its registry.
+pub const MAX_GREETINGS_PER_AUTHORITY: u32 = 16;
+
+/// Per-authority tally of open greetings, stored at `derive_registry_pda`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct RegistryState {
+    // Greetings created by this authority and not yet closed.
+    pub greeting_count: u32,
+
+    // One past the highest index this authority has created an indexed greeting at, so
+    // clients can enumerate `0..next_index` rather than guess. Named greetings don't move
+    // it. Registries created before this field existed start it from zero.
+    pub next_index: u64,
+}
+
+impl RegistryState {
+    // Tags registry account data, distinct from greeting and config accounts.
+    pub const ACCOUNT_DISCRIMINATOR: &'static str = "REGISTRY";
+
+    // Calculate the maximum space needed for a registry account.
+    pub const fn get_max_space_needed() -> usize {
+        checked_sum(&[
+            Self::ACCOUNT_DISCRIMINATOR.len(), // discriminator
+            4,                                 // greeting_count
+            8,                                 // next_index
+        ])
+    }
+
+    // Size of registries created before `next_index` was added. The program grows them
+    // to the current size the next time it writes one.
+    pub(crate) const LEGACY_SPACE: usize = 12;
+}
+
+/// Program-wide counters, stored at `derive_stats_pda`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct StatsState {
+    // Greetings created while the stats account was passed to CreateGreeting.
+    pub total_greetings: u64,
+
+    // Messages written while the stats account was passed to SetGreeting.
+    pub total_updates: u64,
+}
+
+impl StatsState {
+    // Tags stats account data, distinct from every other account kind.
+    pub const ACCOUNT_DISCRIMINATOR: &'static str = "GR_STATS";
+
+    // Calculate the maximum space needed for the stats account.
+    pub const fn get_max_space_needed() -> usize {
+        checked_sum(&[
+            Self::ACCOUNT_DISCRIMINATOR.len(), // discriminator
+            8,                                 // total_greetings
+            8,                                 // total_updates
+        ])
+    }
+}
+
+/// Seed for the stats account PDA.
+pub const STATS_SEED: &[u8] = b"stats";
+
+/// Derives the program's stats PDA. Seeds are `[STATS_SEED]`.
+pub fn derive_stats_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STATS_SEED], program_id)
+}
+
+/// Decodes stats state from raw account data, after checking its discriminator.
+pub fn unpack_stats(data: &[u8]) -> Result<StatsState, ProgramError> {
+    unpack_tagged(StatsState::ACCOUNT_DISCRIMINATOR, data)
+}
+
+// Adds up the field sizes in the `get_max_space_needed` functions, panicking instead of
+// wrapping if the total doesn't fit in `usize`, as it might on a 32-bit target.
+const fn checked_sum(sizes: &[usize]) -> usize {
+    let mut total: usize = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        total = match total.checked_add(sizes[i]) {
+            Some(total) => total,
+            None => panic!("account space overflows usize"),
+        };
+        i += 1;
+    }
+    total
+}
+
+// Evaluating every account size here runs `checked_sum` at compile time, so an overflow
+// fails the build rather than waiting for a runtime panic. Each size must also fit in a
+// single account, which the runtime caps at `MAX_PERMITTED_DATA_LENGTH` (10 MiB); raising
+// a limit like `MAX_MESSAGE_CHARS` past that fails here instead of in CreateGreeting.
+const _: () = {
+    const MAX: usize = MAX_PERMITTED_DATA_LENGTH as usize;
+    assert!(GreetingAccountState::get_max_space_needed() <= MAX);
+    assert!(ConfigState::get_max_space_needed() <= MAX);
+    assert!(RegistryState::get_max_space_needed() <= MAX);
+    assert!(StatsState::get_max_space_needed() <= MAX);
+};
+
+/// Seed prefix for registry account PDAs.
+pub const REGISTRY_SEED: &[u8] = b"registry";
+
+/// Derives the registry PDA counting `authority`'s greetings. Seeds are `[REGISTRY_SEED, authority]`.
+pub fn derive_registry_pda(program_id: &Pubkey, authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRY_SEED, authority.as_ref()], program_id)
+}
+
+/// Decodes registry state from raw account data, after checking its discriminator.
+/// Registries from before `next_index` existed decode with it as zero.
+pub fn unpack_registry(data: &[u8]) -> Result<RegistryState, ProgramError> {
+    if data.len() == RegistryState::LEGACY_SPACE {
+        let mut padded = data.to_vec();
+        padded.resize(RegistryState::get_max_space_needed(), 0);
+        return unpack_tagged(RegistryState::ACCOUNT_DISCRIMINATOR, &padded);
+    }
+    unpack_tagged(RegistryState::ACCOUNT_DISCRIMINATOR, data)
+}
+
+/// Seed prefix for greeting account PDAs.
+pub const GREETING_SEED: &[u8] = b"greeting";
+
+/// Namespace byte mixed into every greeting PDA's seeds.
+///
+/// Bumping this in a release makes `derive_greeting_pda` return entirely new
+/// addresses, so a breaking layout change can start from a clean slate under the
+/// same program ID. Accounts created under the old value are orphaned: clients
+/// will no longer derive them and CreateGreeting will not reuse them, though they
+/// keep their data and lamports and can still be closed by address.
+pub const GREETING_SCHEMA_VERSION: u8 = 1;
+
+/// Encodes a greeting's `index` as its PDA seed: the 8 bytes of the `u64`, little-endian,
+/// so index 1 is `[1, 0, 0, 0, 0, 0, 0, 0]`.
+///
+/// The byte order is part of every indexed greeting's address and must never change:
+/// big-endian bytes would derive different PDAs, and every existing greeting would
+/// silently drop out of reach. `derive_greeting_pda` and CreateGreeting both encode
+/// through here, and `test_index_seed_is_little_endian` pins the bytes.
+pub fn index_seed(index: u64) -> [u8; 8] {
+    index.to_le_bytes()
+}
+
+/// Seed prefix for named greeting PDAs. Deliberately different from `GREETING_SEED`:
+/// seeds are hashed back to back, so an 8-byte name after the same prefix would hash
+/// exactly like an index and the two modes could collide.
+pub const NAMED_GREETING_SEED: &[u8] = b"named_greeting";
+
+/// Derives the greeting PDA named `name` owned by `authority`, giving one greeting per
+/// (wallet, name) pair that can be looked up by name.
+///
+/// Seeds are `[NAMED_GREETING_SEED, [GREETING_SCHEMA_VERSION], authority, name]`. Names are
+/// at most `MAX_NAME_LENGTH` (32) bytes, which fits the 32-byte limit on a single seed.
+/// The address is fixed at creation: renaming the greeting or transferring its authority
+/// leaves it where it is, so it is then no longer found under the new name or authority.
+pub fn derive_named_greeting_pda(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    name: &str,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            NAMED_GREETING_SEED,
+            &[GREETING_SCHEMA_VERSION],
+            authority.as_ref(),
+            name.as_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Derives the `index`-th greeting PDA owned by `authority`.
+///
+/// Seeds are `[GREETING_SEED, [GREETING_SCHEMA_VERSION], authority, index_seed(index)]`.
+/// Returns the address and its canonical bump.
+pub fn derive_greeting_pda(program_id: &Pubkey, authority: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            GREETING_SEED,
+            &[GREETING_SCHEMA_VERSION],
+            authority.as_ref(),
+            &index_seed(index),
+        ],
+        program_id,
+    )
+}
+
+/// The address of a greeting account, typed so it can't be passed where an authority,
+/// payer or other key belongs. Derefs to the `Pubkey`, so `&pda` goes straight to the
+/// instruction builders, which take `&Pubkey`.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GreetingPda(pub Pubkey);
+
+#[cfg(feature = "client")]
+impl GreetingPda {
+    /// The `index`-th greeting of `authority`, see `derive_greeting_pda`.
+    pub fn derive(program_id: &Pubkey, authority: &Pubkey, index: u64) -> Self {
+        GreetingPda(derive_greeting_pda(program_id, authority, index).0)
+    }
+
+    /// The greeting of `authority` named `name`, see `derive_named_greeting_pda`.
+    pub fn derive_named(program_id: &Pubkey, authority: &Pubkey, name: &str) -> Self {
+        GreetingPda(derive_named_greeting_pda(program_id, authority, name).0)
+    }
+}
+
+#[cfg(feature = "client")]
+impl std::ops::Deref for GreetingPda {
+    type Target = Pubkey;
+
+    fn deref(&self) -> &Pubkey {
+        &self.0
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<GreetingPda> for Pubkey {
+    fn from(pda: GreetingPda) -> Self {
+        pda.0
+    }
+}
+
+#[cfg(feature = "client")]
+impl std::fmt::Display for GreetingPda {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Checks that account data starts with `GreetingAccountState::ACCOUNT_DISCRIMINATOR`.
+pub(crate) fn check_discriminator(data: &[u8]) -> Result<(), ProgramError> {
+    if !data.starts_with(GreetingAccountState::ACCOUNT_DISCRIMINATOR.as_bytes()) {
+        msg!("Account data is not tagged as a greeting");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Returns the layout version greeting account `data` was written in, telling the layouts
+/// apart as follows:
+///
+/// - exactly `GreetingAccountStateV0::SPACE` (230) bytes: 0, the unversioned layout, which
+///   has no version byte. Every such account was allocated at that size and no versioned
+///   account ever is: the older versioned layouts were allocated larger, compact greetings
+///   step around it (see `exact_space_needed`) and an unversioned account upgraded in place
+///   drops a byte when it is.
+/// - any other length: the byte after the discriminator, from 1 to `CURRENT_VERSION`.
+///   Lengths alone can't tell the versioned layouts apart, since compact and grown
+///   accounts come in many sizes, but the version byte always can.
+///
+/// Untagged data and an unknown version byte fail with `InvalidAccountData`.
+pub fn greeting_layout_version(data: &[u8]) -> Result<u8, ProgramError> {
+    check_discriminator(data)?;
+    if data.len() == GreetingAccountStateV0::SPACE {
+        return Ok(0);
+    }
+    match data.get(GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()) {
+        Some(&version) if (1..=GreetingAccountState::CURRENT_VERSION).contains(&version) => {
+            Ok(version)
+        }
+        Some(&version) => {
+            msg!("Greeting account has unknown layout version {}", version);
+            Err(ProgramError::InvalidAccountData)
+        }
+        None => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Decodes greeting state from raw account data, after checking its discriminator.
+///
+/// This is the one decode path used both on-chain and by clients. Data in any layout
+/// this program has written decodes, upgraded to the current one as MigrateGreeting
+/// would: see `greeting_layout_version` for how the layout is detected. The returned
+/// state always has `CURRENT_VERSION`, and the program writes it back in the current
+/// layout on the account's next write. The serialized state may be followed by unused
+/// bytes; they are ignored.
+pub fn unpack_greeting(data: &[u8]) -> Result<GreetingAccountState, ProgramError> {
+    match greeting_layout_version(data)? {
+        0 => Ok(unpack_tagged::<GreetingAccountStateV0>(
+            GreetingAccountState::ACCOUNT_DISCRIMINATOR,
+            data,
+        )?
+        .into_current()),
+        GreetingAccountState::CURRENT_VERSION => {
+            unpack_tagged(GreetingAccountState::ACCOUNT_DISCRIMINATOR, data)
+        }
+        version => {
+            // Older versioned layouts are a prefix of the current one, so zero-padding
+            // the data to the current size makes it decode with the new fields at zero.
+            let mut padded = data.to_vec();
+            padded.resize(
+                data.len().max(GreetingAccountState::get_max_space_needed()),
+                0,
+            );
+            let mut state: GreetingAccountState =
+                unpack_tagged(GreetingAccountState::ACCOUNT_DISCRIMINATOR, &padded)?;
+            if version < GreetingAccountState::VISIBILITY_VERSION {
+                state.is_public = true;
+            }
+            state.version = GreetingAccountState::CURRENT_VERSION;
+            Ok(state)
+        }
+    }
+}
+
+/// Parses raw greeting account data, e.g. as fetched by a client, so it composes with `?`.
+/// The same as `unpack_greeting`, older layouts included.
+impl TryFrom<&[u8]> for GreetingAccountState {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        unpack_greeting(data)
+    }
+}
+
+// Whether `data` already holds a greeting in the current layout, which the program can
+// patch in place, rather than one still in an older layout waiting to be rewritten.
+pub(crate) fn has_current_layout(data: &[u8]) -> bool {
+    greeting_layout_version(data) == Ok(GreetingAccountState::CURRENT_VERSION)
+}
+
+/// Checks that `data` starts with `discriminator`, then Borsh-decodes the rest as `T`.
+///
+/// Every account decode goes through here, so adversarial account data can only ever
+/// produce `InvalidAccountData`. The prefix is checked before anything is sliced off,
+/// so data shorter than the discriminator is rejected rather than indexed out of bounds,
+/// and Borsh reports a length prefix running past the end of the data as an error.
+pub(crate) fn unpack_tagged<T: BorshDeserialize>(
+    discriminator: &str,
+    data: &[u8],
+) -> Result<T, ProgramError> {
+    let Some(mut state_data) = data.strip_prefix(discriminator.as_bytes()) else {
+        msg!("Account data is not tagged as {}", discriminator);
+        return Err(ProgramError::InvalidAccountData);
+    };
+    T::deserialize(&mut state_data).map_err(|err| {
+        msg!("Failed to deserialize {} account: {}", discriminator, err);
+        ProgramError::InvalidAccountData
+    })
+}
+
+/// Whether `account` is owned by `program_id` and tagged as a greeting: a cheap filter for
+/// clients scanning many accounts, run before a full `unpack_greeting`. Only the owner and
+/// the discriminator are checked, so the state itself may still fail to decode. Any
+/// mismatch, an account whose data is already borrowed included, returns `false`.
+pub fn is_greeting_account(account: &AccountInfo, program_id: &Pubkey) -> bool {
+    account.owner == program_id
+        && account.try_borrow_data().is_ok_and(|data| {
+            data.starts_with(GreetingAccountState::ACCOUNT_DISCRIMINATOR.as_bytes())
+        })
+}
+
+/// Decodes the greeting state held by `account`. See `unpack_greeting`.
+pub fn unpack_greeting_account(
+    account: &AccountInfo,
+) -> Result<GreetingAccountState, ProgramError> {
+    unpack_greeting(&account.try_borrow_data()?)
+}
+
+/// Zero-copy view of the leading fields of greeting account data, for clients scanning
+/// many accounts that don't want `unpack_greeting` to allocate for every name and message.
+/// `name` and `message` borrow from the data; the other fields are copied out of it.
+///
+/// Fields after `message` sit at offsets that depend on the string lengths before them,
+/// so decode those with `unpack_greeting`.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GreetingView<'a> {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub name: &'a str,
+    pub message: &'a str,
+    pub update_count: u32,
+}
+
+#[cfg(feature = "client")]
+impl<'a> GreetingView<'a> {
+    /// Reads the view from raw greeting account data, after checking its discriminator.
+    /// Malformed data fails with `InvalidAccountData`, as with `unpack_greeting`.
+    pub fn new(data: &'a [u8]) -> Result<Self, ProgramError> {
+        check_discriminator(data)?;
+        let mut rest = &data[GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()..];
+        let [version] = take::<1>(&mut rest)?;
+        let authority = Pubkey::new_from_array(take::<32>(&mut rest)?);
+        let name = take_str(&mut rest)?;
+        let message = take_str(&mut rest)?;
+        let update_count = u32::from_le_bytes(take::<4>(&mut rest)?);
+        Ok(Self {
+            version,
+            authority,
+            name,
+            message,
+            update_count,
+        })
+    }
+}
+
+/// A decoded greeting together with what clients usually work out from it, all from one
+/// call to `GreetingInfo::new`. `state` is exactly what `unpack_greeting` returns; the
+/// other fields are derived from it and the account, and don't update if `state` is
+/// changed afterwards.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreetingInfo {
+    /// The greeting account's address.
+    pub address: GreetingPda,
+    pub state: GreetingAccountState,
+    /// The layout the account was written in, see `greeting_layout_version`.
+    pub layout_version: u8,
+    /// Whether the greeting had expired at the `now` passed in, see `is_expired`.
+    pub is_expired: bool,
+    /// See `GreetingAccountState::rendered_message`.
+    pub rendered_message: String,
+    /// See `GreetingAccountState::summary`.
+    pub summary: String,
+}
+
+#[cfg(feature = "client")]
+impl GreetingInfo {
+    /// Decodes the greeting account at `address` from its raw `data`, as of the unix
+    /// timestamp `now`. Fails like `unpack_greeting` on data that isn't a greeting.
+    pub fn new(address: &Pubkey, data: &[u8], now: i64) -> Result<Self, ProgramError> {
+        let layout_version = greeting_layout_version(data)?;
+        let state = unpack_greeting(data)?;
+        Ok(Self {
+            address: GreetingPda(*address),
+            layout_version,
+            is_expired: state.is_expired(now),
+            rendered_message: state.rendered_message(),
+            summary: state.summary(),
+            state,
+        })
+    }
+}
+
+// Splits the next `N` bytes off the front of `data`.
+#[cfg(feature = "client")]
+fn take<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], ProgramError> {
+    let (bytes, rest) = data
+        .split_first_chunk::<N>()
+        .ok_or(ProgramError::InvalidAccountData)?;
+    *data = rest;
+    Ok(*bytes)
+}
+
+// Splits a Borsh string, a u32 length followed by that many UTF-8 bytes, off the front
+// of `data` without copying it.
+#[cfg(feature = "client")]
+fn take_str<'a>(data: &mut &'a [u8]) -> Result<&'a str, ProgramError> {
+    let len = u32::from_le_bytes(take::<4>(data)?) as usize;
+    let (bytes, rest) = data
+        .split_at_checked(len)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    *data = rest;
+    std::str::from_utf8(bytes).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// Writes the discriminator followed by the Borsh-encoded greeting state. Public, but
+// hidden, so benches/state.rs can compare it with `write_message_in_place`.
+#[doc(hidden)]
+pub fn write_greeting_state(state: &GreetingAccountState, data: &mut [u8]) -> ProgramResult {
+    write_tagged_state(
+        GreetingAccountState::ACCOUNT_DISCRIMINATOR.as_bytes(),
+        state,
+        data,
+    )
+}
+
+// Writes an account discriminator followed by the Borsh encoding of `state`. The encoded
+// size is checked against the account first: serializing straight into a buffer that's
+// too small fails partway, after the bytes before the failure have been overwritten.
+pub(crate) fn write_tagged_state(
+    discriminator: &[u8],
+    state: &impl BorshSerialize,
+    data: &mut [u8],
+) -> ProgramResult {
+    let len = borsh::object_length(state)?;
+    if data.len() < discriminator.len() + len {
+        msg!(
+            "Account holds {} bytes, state needs {}",
+            data.len(),
+            discriminator.len() + len
+        );
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    let (prefix, mut state_data) = data.split_at_mut(discriminator.len());
+    prefix.copy_from_slice(discriminator);
+    state.serialize(&mut state_data)?;
+    Ok(())
+}
+
+// The hot path of SetGreeting and friends: sets `message` on `state`, moving the old one
+// into the history and recording the edit at `now`, and patches `data` to match without
+// re-encoding the whole state. `data` must hold the encoding of `state` as it was loaded.
+//
+// Only the message, `update_count`, `updated_at`, the history slot written and
+// `history_next` are touched; the bytes after a string whose length changes are shifted
+// with `copy_within`, which is `sol_memmove` on-chain. Everything before the message,
+// the authority included, stays as it is. Checked against `write_greeting_state` by
+// `test_in_place_write_matches_full_write`, which must keep passing as fields are added,
+// and timed against it in benches/state.rs, which is why it is public but hidden.
+#[doc(hidden)]
+pub fn write_message_in_place(
+    state: &mut GreetingAccountState,
+    message: String,
+    now: i64,
+    data: &mut [u8],
+) -> ProgramResult {
+    let discriminator_len = GreetingAccountState::ACCOUNT_DISCRIMINATOR.len();
+    let old_end = discriminator_len + borsh::object_length(&*state)?;
+    let old_message_len = state.message.len();
+    let slot = usize::from(state.history_next) % GreetingAccountState::HISTORY_LEN;
+    let evicted_len = state.history[slot].len();
+    // `push_history` skips empty messages, leaving the slot as it is
+    let pushed = !state.message.is_empty();
+
+    // Sized up front, as in `write_tagged_state`, so a state that won't fit fails before
+    // any byte is moved
+    let (added, removed) = if pushed {
+        (
+            message.len() + old_message_len,
+            old_message_len + evicted_len,
+        )
+    } else {
+        (message.len(), old_message_len)
+    };
+    let new_end = old_end + added - removed;
+    if data.len() < old_end.max(new_end) {
+        msg!(
+            "Account holds {} bytes, state needs {}",
+            data.len(),
+            new_end
+        );
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    state.set_message(message, now);
+
+    // version, authority, then the name
+    let message_offset = discriminator_len + 1 + 32 + 4 + state.name.len();
+    let mut end = splice_str(
+        data,
+        message_offset,
+        old_message_len,
+        state.message.as_bytes(),
+        old_end,
+    );
+    let mut offset = message_offset + 4 + state.message.len();
+    data[offset..offset + 4].copy_from_slice(&state.update_count.to_le_bytes());
+    // update_count, bump, created_at
+    offset += 4 + 1 + 8;
+    data[offset..offset + 8].copy_from_slice(&state.updated_at.to_le_bytes());
+    // updated_at, is_initialized, likes, is_public, co_authority, expires_at, total_donations
+    offset += 8
+        + 1
+        + 4
+        + 1
+        + borsh::object_length(&state.co_authority)?
+        + borsh::object_length(&state.expires_at)?
+        + 8;
+
+    let history_offset = |index: usize, state: &GreetingAccountState| {
+        offset
+            + state.history[..index]
+                .iter()
+                .map(|m| 4 + m.len())
+                .sum::<usize>()
+    };
+    if pushed {
+        let slot_offset = history_offset(slot, state);
+        end = splice_str(
+            data,
+            slot_offset,
+            evicted_len,
+            state.history[slot].as_bytes(),
+            end,
+        );
+    }
+    let history_next_offset = history_offset(GreetingAccountState::HISTORY_LEN, state);
+    data[history_next_offset] = state.history_next;
+    // then `frozen`, `rent_payer` and `creator`, untouched: frozen greetings never reach this
+    debug_assert_eq!(history_next_offset + 2 + 32 + 32, end);
+    Ok(())
+}
+
+// Replaces the Borsh string at `offset`, currently `old_len` bytes long, with `new`,
+// shifting the encoded bytes that follow it, up to `end`, to make room or close the gap.
+// Returns where the encoding now ends.
+fn splice_str(data: &mut [u8], offset: usize, old_len: usize, new: &[u8], end: usize) -> usize {
+    let old_tail = offset + 4 + old_len;
+    let new_tail = offset + 4 + new.len();
+    data.copy_within(old_tail..end, new_tail);
+    data[offset..offset + 4].copy_from_slice(&(new.len() as u32).to_le_bytes());
+    data[offset + 4..new_tail].copy_from_slice(new);
+    new_tail + (end - old_tail)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use solana_program::rent::Rent;
+
+    // Printable ASCII, so byte length equals char count and the bounds below are the real limits.
+    pub(crate) fn text(max_len: usize) -> impl Strategy<Value = String> {
+        proptest::string::string_regex(&format!("[ -~]{{1,{}}}", max_len)).unwrap()
+    }
+
+    pub(crate) fn pubkey() -> impl Strategy<Value = Pubkey> {
+        any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+    }
+
+    prop_compose! {
+        fn greeting_state()(
+            authority in pubkey(),
+            name in text(GreetingAccountState::MAX_NAME_LENGTH),
+            message in text(GreetingAccountState::MAX_MESSAGE_CHARS),
+            update_count in any::<u32>(),
+            bump in any::<u8>(),
+            created_at in any::<i64>(),
+            updated_at in any::<i64>(),
+            is_initialized in any::<bool>(),
+            likes in any::<u32>(),
+            is_public in any::<bool>(),
+            co_authority in proptest::option::of(pubkey()),
+            expires_at in proptest::option::of(any::<i64>()),
+            total_donations in any::<u64>(),
+            history in proptest::array::uniform3(text(GreetingAccountState::MAX_MESSAGE_CHARS)),
+            history_next in 0..GreetingAccountState::HISTORY_LEN as u8,
+            frozen in any::<bool>(),
+            rent_payer in pubkey(),
+            creator in pubkey(),
+        ) -> GreetingAccountState {
+            GreetingAccountState {
+                version: GreetingAccountState::CURRENT_VERSION,
+                authority,
+                name,
+                message,
+                update_count,
+                bump,
+                created_at,
+                updated_at,
+                is_initialized,
+                likes,
+                is_public,
+                co_authority,
+                expires_at,
+                total_donations,
+                history,
+                history_next,
+                frozen,
+                rent_payer,
+                creator,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_greeting_state_round_trips(state in greeting_state()) {
+            let bytes = borsh::to_vec(&state).unwrap();
+            prop_assert_eq!(GreetingAccountState::try_from_slice(&bytes).unwrap(), state.clone());
+
+            // Any valid state must also fit the account and decode back through the account path
+            let mut data = vec![0; GreetingAccountState::get_max_space_needed()];
+            write_greeting_state(&state, &mut data).unwrap();
+            prop_assert_eq!(GreetingAccountState::try_from(&data[..]).unwrap(), state.clone());
+            prop_assert_eq!(unpack_greeting(&data).unwrap(), state.clone());
+
+            // Trimmed to the bytes it uses, it still reads as the current layout, bar the
+            // one length reserved for the unversioned one
+            let used = GreetingAccountState::ACCOUNT_DISCRIMINATOR.len() + bytes.len();
+            if used != GreetingAccountStateV0::SPACE {
+                prop_assert_eq!(unpack_greeting(&data[..used]).unwrap(), state);
+            }
+        }
+
+        // A new greeting exactly fills its compact account, bar the one step around the
+        // unversioned layout's size, and decodes from it.
+        #[test]
+        fn test_exact_space_fits_new_greeting(
+            name in text(GreetingAccountState::MAX_NAME_LENGTH),
+            message in text(GreetingAccountState::MAX_MESSAGE_CHARS),
+        ) {
+            let space = GreetingAccountState::exact_space_needed(&name, &message);
+            let state = GreetingAccountState {
+                version: GreetingAccountState::CURRENT_VERSION,
+                name,
+                message,
+                is_initialized: true,
+                is_public: true,
+                ..GreetingAccountState::default()
+            };
+            let len = GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()
+                + borsh::object_length(&state).unwrap();
+            prop_assert!(space == len || (len == GreetingAccountStateV0::SPACE && space == len + 1));
+            prop_assert!(space < GreetingAccountState::get_max_space_needed());
+
+            let mut data = vec![0; space];
+            write_greeting_state(&state, &mut data).unwrap();
+            prop_assert_eq!(GreetingAccountState::try_from(&data[..]).unwrap(), state);
+        }
+    }
+
+    proptest! {
+        // The in-place writer must leave exactly the bytes a full re-encode would, whatever
+        // the string lengths before and after and whichever history slots are in use.
+        #[test]
+        fn test_in_place_write_matches_full_write(
+            mut state in greeting_state(),
+            empty_slots in any::<[bool; GreetingAccountState::HISTORY_LEN]>(),
+            message in text(GreetingAccountState::MAX_MESSAGE_CHARS),
+            now in any::<i64>(),
+        ) {
+            for (slot, empty) in state.history.iter_mut().zip(empty_slots) {
+                if empty {
+                    slot.clear();
+                }
+            }
+            let mut data = vec![0; GreetingAccountState::get_max_space_needed()];
+            write_greeting_state(&state, &mut data).unwrap();
+
+            let mut expected = state.clone();
+            let previous = std::mem::replace(&mut expected.message, message.clone());
+            expected.push_history(previous);
+            expected.update_count = expected.update_count.saturating_add(1);
+            expected.updated_at = now;
+            let mut expected_data = vec![0; data.len()];
+            write_greeting_state(&expected, &mut expected_data).unwrap();
+
+            write_message_in_place(&mut state, message, now, &mut data).unwrap();
+            prop_assert_eq!(&state, &expected);
+            // Past the end of the encoding both hold leftovers, which nothing reads
+            let end = GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()
+                + borsh::to_vec(&expected).unwrap().len();
+            prop_assert_eq!(&data[..end], &expected_data[..end]);
+        }
+    }
+
+    proptest! {
+        // Arbitrary bytes, with and without a valid tag in front, must decode to a state
+        // or an error; a panic here would abort the program on-chain.
+        #[test]
+        fn test_unpack_never_panics(
+            bytes in proptest::collection::vec(any::<u8>(), 0..512),
+            tag in prop_oneof![
+                Just(""),
+                Just(GreetingAccountState::ACCOUNT_DISCRIMINATOR),
+                Just(ConfigState::ACCOUNT_DISCRIMINATOR),
+                Just(RegistryState::ACCOUNT_DISCRIMINATOR),
+            ],
+        ) {
+            let data = [tag.as_bytes(), &bytes].concat();
+            for err in [
+                unpack_greeting(&data).err(),
+                unpack_config(&data).err(),
+                unpack_registry(&data).err(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                prop_assert_eq!(err, ProgramError::InvalidAccountData);
+            }
+            for len in 0..GreetingAccountState::ACCOUNT_DISCRIMINATOR.len() {
+                prop_assert!(unpack_greeting(&data[..len.min(data.len())]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_space_fits_largest_state() {
+        // Usable in const contexts, e.g. to size a stack buffer
+        let mut data = [0u8; GreetingAccountState::get_max_space_needed()];
+        let state = GreetingAccountState {
+            version: GreetingAccountState::CURRENT_VERSION,
+            name: "n".repeat(GreetingAccountState::MAX_NAME_LENGTH),
+            // Four UTF-8 bytes per character, the worst case the space is reserved for
+            message: "\u{1F44B}".repeat(GreetingAccountState::MAX_MESSAGE_CHARS),
+            is_initialized: true,
+            co_authority: Some(Pubkey::new_unique()),
+            expires_at: Some(i64::MAX),
+            history: std::array::from_fn(|_| {
+                "\u{1F44B}".repeat(GreetingAccountState::MAX_MESSAGE_CHARS)
+            }),
+            ..GreetingAccountState::default()
+        };
+
+        // Every field is counted: the largest state fills the account exactly
+        write_greeting_state(&state, &mut data).unwrap();
+        assert_eq!(
+            GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()
+                + borsh::to_vec(&state).unwrap().len(),
+            data.len()
+        );
+    }
+
+    #[test]
+    fn test_legacy_registry_decodes_without_next_index() {
+        let mut data = vec![0; RegistryState::LEGACY_SPACE];
+        data[..8].copy_from_slice(RegistryState::ACCOUNT_DISCRIMINATOR.as_bytes());
+        data[8..].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(
+            unpack_registry(&data).unwrap(),
+            RegistryState {
+                greeting_count: 3,
+                next_index: 0,
+            }
+        );
+    }
+
+    // Summed by hand from the field list, so adding a field or changing a limit has to
+    // update these on purpose.
+    #[test]
+    fn test_space_matches_hand_sum() {
+        assert_eq!(GreetingAccountState::get_max_space_needed(), 2284);
+        assert_eq!(ConfigState::get_max_space_needed(), 1078);
+        assert_eq!(RegistryState::get_max_space_needed(), 20);
+        assert_eq!(StatsState::get_max_space_needed(), 24);
+    }
+
+    #[test]
+    fn test_compact_space_against_max_space() {
+        let compact = GreetingAccountState::exact_space_needed("Alice", "Hello, Solana!");
+        let max = GreetingAccountState::get_max_space_needed();
+        assert_eq!(compact, 183);
+
+        // The fixed account overhead keeps the rent saving below the space saving
+        let rent = Rent::default();
+        let compact_rent = rent.minimum_balance(compact);
+        let max_rent = rent.minimum_balance(max);
+        assert!(compact_rent * 7 < max_rent);
+        assert!(compact_rent * 8 > max_rent);
+
+        // Even the longest name and message leave out the history reservation
+        let longest = GreetingAccountState::exact_space_needed(
+            &"n".repeat(GreetingAccountState::MAX_NAME_LENGTH),
+            &"\u{1F44B}".repeat(GreetingAccountState::MAX_MESSAGE_CHARS),
+        );
+        assert_eq!(
+            longest,
+            max - 32
+                - 8
+                - GreetingAccountState::HISTORY_LEN * GreetingAccountState::MAX_MESSAGE_LENGTH
+        );
+
+        // Sizes that would pass for the unversioned layout are stepped around
+        let message = "m".repeat(GreetingAccountStateV0::SPACE - 164 - 5);
+        assert_eq!(
+            GreetingAccountState::exact_space_needed("Alice", &message),
+            GreetingAccountStateV0::SPACE + 1
+        );
+    }
+
+    #[test]
+    fn test_write_to_undersized_account_changes_nothing() {
+        let state = GreetingAccountState {
+            name: "Alice".to_string(),
+            message: "Hello".to_string(),
+            history: std::array::from_fn(|_| "Earlier".to_string()),
+            version: GreetingAccountState::CURRENT_VERSION,
+            ..GreetingAccountState::default()
+        };
+        let needed = GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()
+            + borsh::to_vec(&state).unwrap().len();
+
+        // One byte short: the write fails up front instead of stopping partway through
+        let mut data = vec![0xAA; needed - 1];
+        assert_eq!(
+            write_greeting_state(&state, &mut data),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+        assert!(data.iter().all(|&byte| byte == 0xAA));
+
+        let mut data = vec![0xAA; needed];
+        write_greeting_state(&state, &mut data).unwrap();
+        assert_eq!(unpack_greeting(&data).unwrap(), state);
+    }
+
+    #[test]
+    fn test_layout_detected_from_length_and_version() {
+        let authority = Pubkey::new_from_array([200; 32]);
+        let tagged = |fields: Vec<u8>, len: usize| {
+            let mut data = [
+                GreetingAccountState::ACCOUNT_DISCRIMINATOR.as_bytes(),
+                &fields,
+            ]
+            .concat();
+            data.resize(len, 0);
+            data
+        };
+        let v0_fields = borsh::to_vec(&(
+            authority,
+            "Alice".to_string(),
+            "Old hello".to_string(),
+            7u32,
+            254u8,
+            100i64,
+            200i64,
+            true,
+        ))
+        .unwrap();
+
+        // 230 bytes: the unversioned layout, upgraded as it's read
+        let v0 = tagged(v0_fields.clone(), GreetingAccountStateV0::SPACE);
+        assert_eq!(greeting_layout_version(&v0), Ok(0));
+        let state = unpack_greeting(&v0).unwrap();
+        assert_eq!(state.version, GreetingAccountState::CURRENT_VERSION);
+        assert_eq!(state.authority, authority);
+        assert_eq!(
+            (state.name.as_str(), state.message.as_str()),
+            ("Alice", "Old hello")
+        );
+        assert_eq!(
+            (state.update_count, state.bump, state.updated_at),
+            (7, 254, 200)
+        );
+        assert!(state.is_public);
+        assert!(state.history().is_empty());
+        // The same bytes at any other length are read by their version byte, here the
+        // authority's first byte, which no layout has
+        let misread = tagged(v0_fields, GreetingAccountStateV0::SPACE + 1);
+        assert_eq!(
+            greeting_layout_version(&misread),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            unpack_greeting(&misread),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // A version 1 account, allocated larger, is read by its version byte
+        let v1 = tagged(
+            borsh::to_vec(&(
+                1u8,
+                authority,
+                "Alice".to_string(),
+                "V1 hello".to_string(),
+                3u32,
+                254u8,
+                100i64,
+                200i64,
+                true,
+            ))
+            .unwrap(),
+            231,
+        );
+        assert_eq!(greeting_layout_version(&v1), Ok(1));
+        let state = unpack_greeting(&v1).unwrap();
+        assert_eq!(state.version, GreetingAccountState::CURRENT_VERSION);
+        assert_eq!(
+            (state.message.as_str(), state.update_count, state.likes),
+            ("V1 hello", 3, 0)
+        );
+        assert!(state.is_public);
+
+        // The current layout reads back as written, at full size or compact
+        let current = GreetingAccountState {
+            version: GreetingAccountState::CURRENT_VERSION,
+            authority,
+            name: "Alice".to_string(),
+            message: "Hello".to_string(),
+            is_initialized: true,
+            ..GreetingAccountState::default()
+        };
+        for len in [
+            GreetingAccountState::get_max_space_needed(),
+            GreetingAccountState::exact_space_needed(&current.name, &current.message),
+        ] {
+            let mut data = vec![0; len];
+            write_greeting_state(&current, &mut data).unwrap();
+            assert_eq!(
+                greeting_layout_version(&data),
+                Ok(GreetingAccountState::CURRENT_VERSION)
+            );
+            assert_eq!(unpack_greeting(&data).unwrap(), current);
+        }
+
+        // Version bytes no build has written are refused
+        for version in [0, GreetingAccountState::CURRENT_VERSION + 1] {
+            let mut data = vec![0; GreetingAccountState::get_max_space_needed()];
+            write_greeting_state(&current, &mut data).unwrap();
+            data[GreetingAccountState::ACCOUNT_DISCRIMINATOR.len()] = version;
+            assert_eq!(
+                unpack_greeting(&data),
+                Err(ProgramError::InvalidAccountData)
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_greeting_account_checks_owner_and_tag() {
+        let program_id = Pubkey::new_unique();
+        let foreign_owner = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let mut greeting_data = vec![0; GreetingAccountState::get_max_space_needed()];
+        write_greeting_state(&GreetingAccountState::default(), &mut greeting_data).unwrap();
+        let mut foreign_data = greeting_data.clone();
+        let mut empty_data = vec![];
+        let mut lamports = [0; 3];
+        let [greeting_lamports, foreign_lamports, empty_lamports] = &mut lamports;
+        let account = |lamports, data, owner| {
+            AccountInfo::new(&key, false, false, lamports, data, owner, false, 0)
+        };
+
+        assert!(is_greeting_account(
+            &account(greeting_lamports, &mut greeting_data, &program_id),
+            &program_id
+        ));
+        // The same bytes under another owner could have been written by anyone
+        assert!(!is_greeting_account(
+            &account(foreign_lamports, &mut foreign_data, &foreign_owner),
+            &program_id
+        ));
+        assert!(!is_greeting_account(
+            &account(empty_lamports, &mut empty_data, &program_id),
+            &program_id
+        ));
+    }
+
+    #[test]
+    fn test_greeting_view_rejects_malformed_data() {
+        let state = GreetingAccountState {
+            name: "Alice".to_string(),
+            message: "Hello".to_string(),
+            ..GreetingAccountState::default()
+        };
+        let mut data = vec![0; GreetingAccountState::get_max_space_needed()];
+        write_greeting_state(&state, &mut data).unwrap();
+        assert_eq!(GreetingView::new(&data).unwrap().message, "Hello");
+
+        let err = Err(ProgramError::InvalidAccountData);
+        // Untagged, cut off inside the message, or with a length running past the end
+        assert_eq!(GreetingView::new(&data[8..]), err);
+        assert_eq!(GreetingView::new(&data[..8 + 1 + 32 + 4 + 5 + 4 + 2]), err);
+        let name_len = 8 + 1 + 32;
+        data[name_len..name_len + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(GreetingView::new(&data), err);
+    }
+
+    #[test]
+    fn test_history_keeps_last_messages_oldest_first() {
+        let mut state = GreetingAccountState::default();
+        assert!(state.history().is_empty());
+
+        state.push_history("one".to_string());
+        state.push_history(String::new());
+        state.push_history("two".to_string());
+        assert_eq!(state.history(), ["one", "two"]);
+
+        // One more than fits: "one" is evicted and the rest stay in order
+        state.push_history("three".to_string());
+        state.push_history("four".to_string());
+        assert_eq!(state.history(), ["two", "three", "four"]);
+        state.push_history("five".to_string());
+        assert_eq!(state.history(), ["three", "four", "five"]);
+    }
+
+    #[test]
+    fn test_is_expired_at_and_after_expiry() {
+        let mut state = GreetingAccountState::default();
+        assert!(!state.is_expired(i64::MAX));
+
+        state.expires_at = Some(100);
+        assert!(!state.is_expired(99));
+        assert!(state.is_expired(100));
+        assert!(state.is_expired(101));
+    }
+
+    #[test]
+    fn test_rendered_message_fills_in_the_name() {
+        let mut state = GreetingAccountState {
+            name: "Alice".to_string(),
+            message: "Hello {name}! Bye, {name}.".to_string(),
+            ..GreetingAccountState::default()
+        };
+        assert_eq!(state.rendered_message(), "Hello Alice! Bye, Alice.");
+
+        // Without the placeholder, or with unknown ones, the message is shown as written
+        state.message = "Hello {friend}, {NAME} and {name".to_string();
+        assert_eq!(state.rendered_message(), state.message);
+
+        // A name that looks like a placeholder is inserted as is
+        state.name = "{name}".to_string();
+        state.message = "Hi {name}".to_string();
+        assert_eq!(state.rendered_message(), "Hi {name}");
+    }
+
+    #[test]
+    fn test_greeting_info_matches_the_raw_state() {
+        let address = Pubkey::new_unique();
+        let state = GreetingAccountState {
+            version: GreetingAccountState::CURRENT_VERSION,
+            name: "Alice".to_string(),
+            message: "Hello {name}!".to_string(),
+            expires_at: Some(100),
+            ..GreetingAccountState::default()
+        };
+        let mut data = vec![0; GreetingAccountState::get_max_space_needed()];
+        write_greeting_state(&state, &mut data).unwrap();
+
+        let info = GreetingInfo::new(&address, &data, 99).unwrap();
+        assert_eq!(*info.address, address);
+        assert_eq!(info.state, unpack_greeting(&data).unwrap());
+        assert_eq!(info.layout_version, greeting_layout_version(&data).unwrap());
+        assert!(!info.is_expired);
+        assert_eq!(info.rendered_message, "Hello Alice!");
+        assert_eq!(info.rendered_message, info.state.rendered_message());
+        assert_eq!(info.summary, info.state.summary());
+        assert!(GreetingInfo::new(&address, &data, 100).unwrap().is_expired);
+
+        assert_eq!(
+            GreetingInfo::new(&address, &data[8..], 0),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_summary_shortens_authority() {
+        let state = GreetingAccountState {
+            authority: Pubkey::new_from_array([7; 32]),
+            name: "Alice".to_string(),
+            message: "Hello \u{1F44B}".to_string(),
+            update_count: 3,
+            ..GreetingAccountState::default()
+        };
+        let authority = state.authority.to_string();
+        assert_eq!(
+            state.summary(),
+            format!(
+                "Alice: Hello \u{1F44B} (edited 3x by {}..{})",
+                &authority[..4],
+                &authority[authority.len() - 4..]
+            )
+        );
+        assert_eq!(
+            GreetingAccountState::default().summary(),
+            ":  (edited 0x by 1111..1111)"
+        );
+    }
+
+    #[test]
+    fn test_legacy_config_decodes_without_allowlist() {
+        // The longest default message filled a legacy config to its last byte
+        let legacy = ConfigState {
+            admin: Pubkey::new_unique(),
+            default_message: "m".repeat(GreetingAccountState::MAX_MESSAGE_LENGTH),
+            paused: true,
+            ..ConfigState::default()
+        };
+        let mut data = ConfigState::ACCOUNT_DISCRIMINATOR.as_bytes().to_vec();
+        data.extend(borsh::to_vec(&legacy.admin).unwrap());
+        data.extend(borsh::to_vec(&legacy.default_message).unwrap());
+        data.push(1);
+        assert_eq!(data.len(), ConfigState::LEGACY_SPACES[0]);
+
+        let decoded = unpack_config(&data).unwrap();
+        assert_eq!(decoded.default_message, legacy.default_message);
+        assert!(decoded.paused);
+        assert!(!decoded.allowlist_enabled);
+        assert!(decoded.allowlist.is_empty());
+        assert_eq!(decoded.min_update_interval_secs, 0);
+
+        // A config from before the update interval, its allowlist full to the last byte
+        data.push(1);
+        data.extend(
+            borsh::to_vec(&vec![Pubkey::new_unique(); ConfigState::MAX_ALLOWLIST_LEN]).unwrap(),
+        );
+        assert_eq!(data.len(), ConfigState::LEGACY_SPACES[1]);
+        let decoded = unpack_config(&data).unwrap();
+        assert!(decoded.paused && decoded.allowlist_enabled);
+        assert_eq!(decoded.allowlist.len(), ConfigState::MAX_ALLOWLIST_LEN);
+        assert_eq!(decoded.min_update_interval_secs, 0);
+    }
+
+    #[test]
+    fn test_config_round_trips() {
+        let config_state = ConfigState {
+            admin: Pubkey::new_unique(),
+            default_message: "Welcome!".to_string(),
+            paused: true,
+            allowlist_enabled: true,
+            allowlist: vec![Pubkey::new_unique(); ConfigState::MAX_ALLOWLIST_LEN],
+            min_update_interval_secs: 90,
+        };
+        let mut data = vec![0; ConfigState::get_max_space_needed()];
+        write_tagged_state(
+            ConfigState::ACCOUNT_DISCRIMINATOR.as_bytes(),
+            &config_state,
+            &mut data,
+        )
+        .unwrap();
+
+        let decoded = unpack_config(&data).unwrap();
+        assert_eq!(decoded.admin, config_state.admin);
+        assert_eq!(decoded.default_message, "Welcome!");
+        assert!(decoded.paused);
+        assert!(decoded.allowlist_enabled);
+        assert_eq!(decoded.allowlist, config_state.allowlist);
+        assert_eq!(decoded.min_update_interval_secs, 90);
+        // Greeting data is never mistaken for config
+        assert_eq!(
+            unpack_greeting(&data).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+
+    #[test]
+    fn test_greeting_pda_wraps_the_derived_address() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let pda = GreetingPda::derive(&program_id, &authority, 7);
+        assert_eq!(*pda, derive_greeting_pda(&program_id, &authority, 7).0);
+        assert_eq!(pda, GreetingPda(pda.0));
+        assert_eq!(Pubkey::from(pda), pda.0);
+        assert_eq!(pda.to_string(), pda.0.to_string());
+        let named = GreetingPda::derive_named(&program_id, &authority, "Alice");
+        assert_eq!(
+            *named,
+            derive_named_greeting_pda(&program_id, &authority, "Alice").0
+        );
+        assert_ne!(named, pda);
+
+        // Pubkey methods and `&Pubkey` parameters work through the deref
+        let takes_pubkey = |key: &Pubkey| *key;
+        assert_eq!(takes_pubkey(&pda), pda.0);
+        assert_eq!(pda.to_bytes(), pda.0.to_bytes());
+    }
+
+    // A fixed index against fixed bytes: byte order mistakes in `index_seed` fail here
+    // rather than moving every indexed greeting to a new address.
+    #[test]
+    fn test_index_seed_is_little_endian() {
+        assert_eq!(index_seed(0), [0; 8]);
+        assert_eq!(index_seed(1), [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(index_seed(0x0102_0304_0506_0708), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(index_seed(u64::MAX), [0xff; 8]);
+    }
+
+    #[test]
+    fn test_greeting_pda_seeds_include_schema_version() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let (pda, bump) = derive_greeting_pda(&program_id, &authority, 7);
+
+        let expected = Pubkey::create_program_address(
+            &[
+                GREETING_SEED,
+                &[GREETING_SCHEMA_VERSION],
+                authority.as_ref(),
+                &7u64.to_le_bytes(),
+                &[bump],
+            ],
+            &program_id,
+        )
+        .unwrap();
+        assert_eq!(pda, expected);
+    }
+}
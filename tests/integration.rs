@@ -0,0 +1,2449 @@
+//! End-to-end tests running the program natively inside `solana-program-test`,
+//! so the System Program CPI, rent and PDA signing all behave as on-chain.
+
+use greeting_program::{
+    cpi, derive_config_pda, derive_greeting_pda, derive_named_greeting_pda, derive_registry_pda,
+    derive_stats_pda, greeting_layout_version, instruction, process_instruction, unpack_config,
+    unpack_greeting, unpack_registry, unpack_stats, ConfigState, GreetingAccountState,
+    GreetingError, StatsState,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE},
+    program::invoke_signed,
+    program_error::ProgramError,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest, ProgramTestBanksClientExt};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+fn program_test(program_id: Pubkey) -> ProgramTest {
+    ProgramTest::new(
+        "greeting_program",
+        program_id,
+        processor!(process_instruction),
+    )
+}
+
+async fn fetch_greeting(banks_client: &mut BanksClient, address: Pubkey) -> GreetingAccountState {
+    let account = banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .expect("greeting account exists");
+    unpack_greeting(&account.data).unwrap()
+}
+
+async fn create_greeting(
+    banks_client: &mut BanksClient,
+    program_id: &Pubkey,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+) -> Pubkey {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_greeting(
+            program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Hello, Solana!".to_string(),
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    derive_greeting_pda(program_id, &payer.pubkey(), 0).0
+}
+
+/// Most compute units a `CreateGreeting` may consume on the SBF build before the budget
+/// test fails. Raise it deliberately, in the change that explains the extra cost.
+///
+/// Set with headroom above the program's syscall and CPI costs; tighten it to the figure
+/// the test prints under `cargo test-sbf`.
+const CREATE_GREETING_COMPUTE_BUDGET: u64 = 30_000;
+
+// Whether the program under test is the SBF build. `cargo test-sbf` sets `SBF_OUT_DIR`
+// (`BPF_OUT_DIR` for older tooling), and `ProgramTest` then loads the built program
+// instead of calling `process_instruction` natively.
+fn running_sbf_build() -> bool {
+    std::env::var_os("SBF_OUT_DIR").is_some() || std::env::var_os("BPF_OUT_DIR").is_some()
+}
+
+// Only asserts against the SBF build: natively, the program runs as plain Rust and only the
+// runtime's CPIs are metered, so program-side work like a redundant PDA search goes unseen.
+#[tokio::test]
+async fn test_create_greeting_stays_within_compute_budget() {
+    if !running_sbf_build() {
+        println!("Skipping the compute budget check: run it with `cargo test-sbf`");
+        return;
+    }
+    let program_id = Pubkey::new_unique();
+    let (banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_greeting(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Hello, Solana!".to_string(),
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let outcome = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+    let consumed = outcome.metadata.unwrap().compute_units_consumed;
+    println!("CreateGreeting consumed {} compute units", consumed);
+    assert!(
+        consumed <= CREATE_GREETING_COMPUTE_BUDGET,
+        "CreateGreeting consumed {} compute units, budget is {}",
+        consumed,
+        CREATE_GREETING_COMPUTE_BUDGET
+    );
+}
+
+#[tokio::test]
+async fn test_create_greeting() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let account = banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id);
+    assert_eq!(
+        account.data.len(),
+        GreetingAccountState::get_max_space_needed()
+    );
+    // Funded for exactly rent exemption, no more
+    let rent = banks_client.get_rent().await.unwrap();
+    assert_eq!(account.lamports, rent.minimum_balance(account.data.len()));
+
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.authority, payer.pubkey());
+    assert_eq!(state.name, "Alice");
+    assert_eq!(state.message, "Hello, Solana!");
+    assert_eq!(state.update_count, 0);
+    assert_eq!(
+        state.bump,
+        derive_greeting_pda(&program_id, &payer.pubkey(), 0).1
+    );
+    assert!(state.is_initialized);
+}
+
+#[tokio::test]
+async fn test_set_greeting_from_wrong_signer_fails() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let impostor = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_greeting(
+            &program_id,
+            &impostor.pubkey(),
+            &greeting_pda,
+            "Not yours".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &impostor],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::InvalidAuthority as u32)
+        )
+    );
+
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.message, "Hello, Solana!");
+}
+
+#[tokio::test]
+async fn test_migrate_legacy_greeting() {
+    let program_id = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let (greeting_pda, bump) = derive_greeting_pda(&program_id, &authority.pubkey(), 0);
+
+    // A greeting written before the `version` field existed, at the size every such account had
+    let legacy_space = 230;
+    let mut legacy_data = GreetingAccountState::ACCOUNT_DISCRIMINATOR
+        .as_bytes()
+        .to_vec();
+    legacy_data.extend(
+        borsh::to_vec(&(
+            authority.pubkey(),
+            "Alice".to_string(),
+            "Old hello".to_string(),
+            7u32,
+            bump,
+            100i64,
+            200i64,
+            true,
+        ))
+        .unwrap(),
+    );
+    legacy_data.resize(legacy_space, 0);
+
+    let mut program_test = program_test(program_id);
+    program_test.add_account(
+        greeting_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(legacy_space),
+            data: legacy_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        authority.pubkey(),
+        Account::new(1_000_000_000, 0, &solana_sdk::system_program::ID),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let migrate = instruction::migrate_greeting(&program_id, &authority.pubkey(), &greeting_pda);
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&migrate),
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        account.data.len(),
+        GreetingAccountState::get_max_space_needed()
+    );
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.version, GreetingAccountState::CURRENT_VERSION);
+    assert_eq!(state.authority, authority.pubkey());
+    assert_eq!(state.message, "Old hello");
+    assert_eq!(state.update_count, 7);
+    assert_eq!(state.updated_at, 200);
+
+    // Migrating an already-current account is a no-op
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[migrate],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let after = banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.data, account.data);
+    assert_eq!(after.lamports, account.lamports);
+}
+
+#[tokio::test]
+async fn test_grow_greeting() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let original_size = GreetingAccountState::get_max_space_needed() as u64;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::grow_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &payer.pubkey(),
+            original_size + 100,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len() as u64, original_size + 100);
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.message, "Hello, Solana!");
+
+    // Growing past the per-instruction realloc limit is rejected
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::grow_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &payer.pubkey(),
+            original_size + 100 + MAX_PERMITTED_DATA_INCREASE as u64 + 1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::InvalidRealloc)
+    );
+}
+
+#[tokio::test]
+async fn test_compact_greeting_is_smaller_until_grown() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let full_pda = create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_compact_greeting(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Hello, Solana!".to_string(),
+            1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let (compact_pda, _) = derive_greeting_pda(&program_id, &payer.pubkey(), 1);
+
+    // Same greeting, sized to fit, funded for exactly that size
+    let full = banks_client.get_account(full_pda).await.unwrap().unwrap();
+    let compact = banks_client
+        .get_account(compact_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        compact.data.len(),
+        GreetingAccountState::exact_space_needed("Alice", "Hello, Solana!")
+    );
+    assert!(compact.data.len() < full.data.len());
+    let rent = banks_client.get_rent().await.unwrap();
+    assert_eq!(compact.lamports, rent.minimum_balance(compact.data.len()));
+    assert!(compact.lamports < full.lamports);
+    let state = GreetingAccountState::try_from(&compact.data[..]).unwrap();
+    assert_eq!(state.message, "Hello, Solana!");
+
+    // Editing has no room until the account is grown
+    let set_greeting = |message: &str| {
+        Transaction::new_signed_with_payer(
+            &[instruction::set_greeting(
+                &program_id,
+                &payer.pubkey(),
+                &compact_pda,
+                message.to_string(),
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            recent_blockhash,
+        )
+    };
+    let err = banks_client
+        .process_transaction(set_greeting("Hi"))
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::AccountDataTooSmall)
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::grow_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &compact_pda,
+            &payer.pubkey(),
+            GreetingAccountState::get_max_space_needed() as u64,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    banks_client
+        .process_transaction(set_greeting("Hello again"))
+        .await
+        .unwrap();
+    let state = fetch_greeting(&mut banks_client, compact_pda).await;
+    assert_eq!(state.message, "Hello again");
+    assert_eq!(state.history(), vec!["Hello, Solana!"]);
+}
+
+#[tokio::test]
+async fn test_migrate_versioned_greeting() {
+    let program_id = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let (greeting_pda, bump) = derive_greeting_pda(&program_id, &authority.pubkey(), 0);
+
+    // A version 1 greeting, from before `likes` was appended
+    let v1_space = 231;
+    let mut v1_data = GreetingAccountState::ACCOUNT_DISCRIMINATOR
+        .as_bytes()
+        .to_vec();
+    v1_data.extend(
+        borsh::to_vec(&(
+            1u8,
+            authority.pubkey(),
+            "Alice".to_string(),
+            "V1 hello".to_string(),
+            3u32,
+            bump,
+            100i64,
+            200i64,
+            true,
+        ))
+        .unwrap(),
+    );
+    v1_data.resize(v1_space, 0);
+
+    let mut program_test = program_test(program_id);
+    program_test.add_account(
+        greeting_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(v1_space),
+            data: v1_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        authority.pubkey(),
+        Account::new(1_000_000_000, 0, &solana_sdk::system_program::ID),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::migrate_greeting(
+            &program_id,
+            &authority.pubkey(),
+            &greeting_pda,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        account.data.len(),
+        GreetingAccountState::get_max_space_needed()
+    );
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.version, GreetingAccountState::CURRENT_VERSION);
+    assert_eq!(state.message, "V1 hello");
+    assert_eq!(state.update_count, 3);
+    assert_eq!(state.likes, 0);
+    // Greetings from before visibility existed stay public
+    assert!(state.is_public);
+}
+
+#[tokio::test]
+async fn test_set_greeting_upgrades_older_layouts_in_place() {
+    let program_id = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let (v0_pda, v0_bump) = derive_greeting_pda(&program_id, &authority.pubkey(), 0);
+    let (v1_pda, v1_bump) = derive_greeting_pda(&program_id, &authority.pubkey(), 1);
+
+    // One greeting from before the `version` field, at the size every such account had,
+    // and one version 1 greeting, from before `likes`
+    let tagged = |fields: Vec<u8>, len: usize| {
+        let mut data = GreetingAccountState::ACCOUNT_DISCRIMINATOR
+            .as_bytes()
+            .to_vec();
+        data.extend(fields);
+        data.resize(len, 0);
+        Account {
+            lamports: Rent::default().minimum_balance(len),
+            data,
+            owner: program_id,
+            ..Account::default()
+        }
+    };
+    let mut program_test = program_test(program_id);
+    program_test.add_account(
+        v0_pda,
+        tagged(
+            borsh::to_vec(&(
+                authority.pubkey(),
+                "Alice".to_string(),
+                "Old hello".to_string(),
+                7u32,
+                v0_bump,
+                100i64,
+                200i64,
+                true,
+            ))
+            .unwrap(),
+            230,
+        ),
+    );
+    program_test.add_account(
+        v1_pda,
+        tagged(
+            borsh::to_vec(&(
+                1u8,
+                authority.pubkey(),
+                "Alice".to_string(),
+                "V1 hello".to_string(),
+                3u32,
+                v1_bump,
+                100i64,
+                200i64,
+                true,
+            ))
+            .unwrap(),
+            231,
+        ),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // Both read without MigrateGreeting
+    assert_eq!(
+        fetch_greeting(&mut banks_client, v0_pda).await.message,
+        "Old hello"
+    );
+    assert_eq!(
+        fetch_greeting(&mut banks_client, v1_pda).await.message,
+        "V1 hello"
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::set_greeting(&program_id, &authority.pubkey(), &v0_pda, "Hi".to_string()),
+            instruction::set_greeting(&program_id, &authority.pubkey(), &v1_pda, "Hey".to_string()),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The write left both in the current layout. The unversioned one dropped a byte,
+    // so its length no longer reads as that layout, and both stay rent-exempt.
+    for (address, len, old_message, update_count) in
+        [(v0_pda, 229, "Old hello", 8), (v1_pda, 231, "V1 hello", 4)]
+    {
+        let account = banks_client.get_account(address).await.unwrap().unwrap();
+        assert_eq!(account.data.len(), len);
+        assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+        assert_eq!(
+            greeting_layout_version(&account.data),
+            Ok(GreetingAccountState::CURRENT_VERSION)
+        );
+        let state = unpack_greeting(&account.data).unwrap();
+        assert_eq!(state.history(), [old_message]);
+        assert_eq!(state.update_count, update_count);
+        assert!(state.is_public);
+    }
+    assert_eq!(
+        fetch_greeting(&mut banks_client, v0_pda).await.message,
+        "Hi"
+    );
+    assert_eq!(
+        fetch_greeting(&mut banks_client, v1_pda).await.message,
+        "Hey"
+    );
+}
+
+#[tokio::test]
+async fn test_reset_greeting_uses_config_default() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_config(&program_id, &payer.pubkey(), "Welcome!".to_string()),
+            instruction::update_config(&program_id, &payer.pubkey(), "Gm!".to_string()),
+            instruction::reset_greeting(&program_id, &payer.pubkey(), &greeting_pda),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let config = banks_client
+        .get_account(derive_config_pda(&program_id).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unpack_config(&config.data).unwrap().admin, payer.pubkey());
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.message, "Gm!");
+
+    // Anyone other than the admin is refused
+    let impostor = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::update_config(
+            &program_id,
+            &impostor.pubkey(),
+            "Hijacked".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &impostor],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::InvalidAdmin as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_pause_blocks_and_unblocks_changes() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_config(&program_id, &payer.pubkey(), "Welcome!".to_string()),
+            instruction::set_paused(&program_id, &payer.pubkey(), true),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let set = instruction::set_greeting(
+        &program_id,
+        &payer.pubkey(),
+        &greeting_pda,
+        "While paused".to_string(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&set),
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::ProgramPaused as u32)
+        )
+    );
+
+    // Unpausing lets the same change through
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::set_paused(&program_id, &payer.pubkey(), false),
+            set,
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.message, "While paused");
+}
+
+#[tokio::test]
+async fn test_min_update_interval_spaces_out_edits() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let greeting_pda = create_greeting(
+        &mut context.banks_client,
+        &program_id,
+        &context.payer,
+        context.last_blockhash,
+    )
+    .await;
+    let admin = context.payer.pubkey();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_config(&program_id, &admin, "Welcome!".to_string()),
+            instruction::set_min_update_interval(&program_id, &admin, 60),
+        ],
+        Some(&admin),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let set = |message: &str| {
+        instruction::set_greeting(&program_id, &admin, &greeting_pda, message.to_string())
+    };
+
+    // Right after creation the greeting was just updated
+    let transaction = Transaction::new_signed_with_payer(
+        &[set("Too soon")],
+        Some(&admin),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::TooSoon as u32)
+        )
+    );
+
+    // A minute on, one edit goes through and starts the wait over
+    let updated_at = fetch_greeting(&mut context.banks_client, greeting_pda)
+        .await
+        .updated_at;
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    context.warp_to_slot(slot + 1_000).unwrap();
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = updated_at + 60;
+    context.set_sysvar(&clock);
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[set("On time"), set("Right after")],
+        Some(&admin),
+        &[&context.payer],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(GreetingError::TooSoon as u32)
+        )
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[set("On time")],
+        Some(&admin),
+        &[&context.payer],
+        recent_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let state = fetch_greeting(&mut context.banks_client, greeting_pda).await;
+    assert_eq!(state.message, "On time");
+    assert_eq!(state.updated_at, updated_at + 60);
+
+    // Zero turns the limit off
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::set_min_update_interval(&program_id, &admin, 0),
+            set("Any time"),
+        ],
+        Some(&admin),
+        &[&context.payer],
+        recent_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let state = fetch_greeting(&mut context.banks_client, greeting_pda).await;
+    assert_eq!(state.message, "Any time");
+}
+
+#[tokio::test]
+async fn test_min_update_interval_covers_batch_and_append() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let greeting_pda = create_greeting(
+        &mut context.banks_client,
+        &program_id,
+        &context.payer,
+        context.last_blockhash,
+    )
+    .await;
+    let admin = context.payer.pubkey();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_config(&program_id, &admin, "Welcome!".to_string()),
+            instruction::set_min_update_interval(&program_id, &admin, 60),
+        ],
+        Some(&admin),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let batch = |message: &str| {
+        instruction::batch_set_greeting(
+            &program_id,
+            &admin,
+            vec![(greeting_pda, message.to_string())],
+        )
+    };
+    let append = |suffix: &str| {
+        instruction::append_to_message(&program_id, &admin, &greeting_pda, suffix.to_string())
+    };
+
+    // Right after creation neither gets around the wait
+    for edit in [batch("Too soon"), append(", too soon")] {
+        let transaction = Transaction::new_signed_with_payer(
+            &[edit],
+            Some(&admin),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+        let err = context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(GreetingError::TooSoon as u32)
+            )
+        );
+    }
+
+    // A minute on, each may edit once, but not right after the other
+    let mut updated_at = fetch_greeting(&mut context.banks_client, greeting_pda)
+        .await
+        .updated_at;
+    for (edit, message) in [(batch("Batched"), "Batched"), (append("!"), "Batched!")] {
+        let slot = context.banks_client.get_root_slot().await.unwrap();
+        context.warp_to_slot(slot + 1_000).unwrap();
+        let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp = updated_at + 60;
+        context.set_sysvar(&clock);
+        let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[edit.clone(), append("?")],
+            Some(&admin),
+            &[&context.payer],
+            recent_blockhash,
+        );
+        let err = context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(GreetingError::TooSoon as u32)
+            )
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[edit],
+            Some(&admin),
+            &[&context.payer],
+            recent_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+        let state = fetch_greeting(&mut context.banks_client, greeting_pda).await;
+        assert_eq!(state.message, message);
+        assert_eq!(state.updated_at, updated_at + 60);
+        updated_at = state.updated_at;
+    }
+}
+
+#[tokio::test]
+async fn test_allowlist_gates_greeting_creation() {
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let outsider = Keypair::new();
+    let config_pda = derive_config_pda(&program_id).0;
+
+    // A config from before the allowlist existed, its default message as long as allowed,
+    // so the allowlist only fits once the config has been grown
+    let default_message = "m".repeat(GreetingAccountState::MAX_MESSAGE_LENGTH);
+    let mut legacy_data = ConfigState::ACCOUNT_DISCRIMINATOR.as_bytes().to_vec();
+    legacy_data.extend(borsh::to_vec(&(admin.pubkey(), &default_message, false)).unwrap());
+    assert_eq!(legacy_data.len(), 557);
+
+    let mut program_test = program_test(program_id);
+    program_test.add_account(
+        config_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(legacy_data.len()),
+            data: legacy_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    for key in [admin.pubkey(), outsider.pubkey()] {
+        program_test.add_account(
+            key,
+            Account::new(1_000_000_000, 0, &solana_sdk::system_program::ID),
+        );
+    }
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let admin_transaction = |instructions: &[Instruction]| {
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &[&payer, &admin],
+            recent_blockhash,
+        )
+    };
+    let create = |creator: &Keypair, index: u64| {
+        Transaction::new_signed_with_payer(
+            &[instruction::create_greeting(
+                &program_id,
+                &creator.pubkey(),
+                "Alice".to_string(),
+                "Hello, Solana!".to_string(),
+                index,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer, creator],
+            recent_blockhash,
+        )
+    };
+    let not_allowlisted = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(GreetingError::NotAllowlisted as u32),
+    );
+
+    // Open to everyone until the gate is turned on
+    banks_client
+        .process_transaction(create(&outsider, 0))
+        .await
+        .unwrap();
+
+    banks_client
+        .process_transaction(admin_transaction(&[
+            instruction::set_allowlist_enabled(&program_id, &admin.pubkey(), true),
+            instruction::add_to_allowlist(&program_id, &admin.pubkey(), &admin.pubkey()),
+        ]))
+        .await
+        .unwrap();
+    let config = banks_client.get_account(config_pda).await.unwrap().unwrap();
+    assert_eq!(config.data.len(), ConfigState::get_max_space_needed());
+    assert!(Rent::default().is_exempt(config.lamports, config.data.len()));
+    let config_state = unpack_config(&config.data).unwrap();
+    assert!(config_state.allowlist_enabled);
+    assert_eq!(config_state.allowlist, vec![admin.pubkey()]);
+    assert_eq!(config_state.default_message, default_message);
+
+    // Listed creators get through, everyone else is refused
+    let err = banks_client
+        .process_transaction(create(&outsider, 1))
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, not_allowlisted);
+    banks_client
+        .process_transaction(create(&admin, 0))
+        .await
+        .unwrap();
+
+    // Removing a creator refuses it from then on; its greeting stays
+    banks_client
+        .process_transaction(admin_transaction(&[instruction::remove_from_allowlist(
+            &program_id,
+            &admin.pubkey(),
+            &admin.pubkey(),
+        )]))
+        .await
+        .unwrap();
+    let err = banks_client
+        .process_transaction(create(&admin, 1))
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(err, not_allowlisted);
+    let (admin_greeting, _) = derive_greeting_pda(&program_id, &admin.pubkey(), 0);
+    assert_eq!(
+        fetch_greeting(&mut banks_client, admin_greeting)
+            .await
+            .message,
+        "Hello, Solana!"
+    );
+
+    // Turning the gate off lets everyone create again
+    banks_client
+        .process_transaction(admin_transaction(&[instruction::set_allowlist_enabled(
+            &program_id,
+            &admin.pubkey(),
+            false,
+        )]))
+        .await
+        .unwrap();
+    banks_client
+        .process_transaction(create(&outsider, 2))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_rename_greeting() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::rename_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            "Bob".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.name, "Bob");
+    assert_eq!(state.message, "Hello, Solana!");
+    assert_eq!(state.update_count, 1);
+}
+
+#[tokio::test]
+async fn test_frozen_greeting_rejects_changes() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::freeze_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            None,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert!(fetch_greeting(&mut banks_client, greeting_pda).await.frozen);
+
+    for change in [
+        instruction::set_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            "Changed".to_string(),
+        ),
+        instruction::rename_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            "Bob".to_string(),
+        ),
+    ] {
+        let transaction = Transaction::new_signed_with_payer(
+            &[change],
+            Some(&payer.pubkey()),
+            &[&payer],
+            recent_blockhash,
+        );
+        let err = banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(GreetingError::GreetingFrozen as u32)
+            )
+        );
+    }
+
+    // Still readable, and unchanged
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.name, "Alice");
+    assert_eq!(state.message, "Hello, Solana!");
+    assert_eq!(state.update_count, 0);
+}
+
+#[tokio::test]
+async fn test_registry_counts_open_greetings() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let registry_pda = derive_registry_pda(&program_id, &payer.pubkey()).0;
+
+    let registry = banks_client
+        .get_account(registry_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unpack_registry(&registry.data).unwrap().greeting_count, 1);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::close_greeting_account(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let registry = banks_client
+        .get_account(registry_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unpack_registry(&registry.data).unwrap().greeting_count, 0);
+}
+
+#[tokio::test]
+async fn test_closing_a_transferred_greeting_frees_its_creators_slot() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let receiver = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &receiver.pubkey(),
+            1_000_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    // The receiver holds one greeting of its own, charged to its own registry
+    create_greeting(&mut banks_client, &program_id, &receiver, recent_blockhash).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::transfer_authority(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            None,
+            receiver.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.authority, receiver.pubkey());
+    assert_eq!(greeting.creator, payer.pubkey());
+
+    // The receiver's registry was never charged for this greeting, so it can't be credited
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::close_greeting_account(
+            &program_id,
+            &receiver.pubkey(),
+            &greeting_pda,
+            &receiver.pubkey(),
+        )],
+        Some(&receiver.pubkey()),
+        &[&receiver],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::PdaMismatch as u32)
+        )
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::close_transferred_greeting(
+            &program_id,
+            &receiver.pubkey(),
+            &payer.pubkey(),
+            &greeting_pda,
+            &receiver.pubkey(),
+        )],
+        Some(&receiver.pubkey()),
+        &[&receiver],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    for (authority, count) in [(payer.pubkey(), 0), (receiver.pubkey(), 1)] {
+        let registry = banks_client
+            .get_account(derive_registry_pda(&program_id, &authority).0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            unpack_registry(&registry.data).unwrap().greeting_count,
+            count
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_close_to_rent_payer_refunds_the_creator() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.rent_payer, payer.pubkey());
+
+    // Another destination is refused, even with the authority signing
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::close_greeting_to_rent_payer(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &Pubkey::new_unique(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::InvalidArgument)
+    );
+
+    let greeting_lamports = banks_client.get_balance(greeting_pda).await.unwrap();
+    let payer_lamports = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::close_greeting_to_rent_payer(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let fee = banks_client
+        .get_fee_for_message(transaction.message.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        banks_client.get_balance(payer.pubkey()).await.unwrap(),
+        payer_lamports + greeting_lamports - fee
+    );
+}
+
+#[tokio::test]
+async fn test_create_greeting_with_bump() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let (greeting_pda, bump) = derive_greeting_pda(&program_id, &payer.pubkey(), 0);
+
+    // A wrong bump doesn't lead to the passed address
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_greeting_with_bump(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Hello, Solana!".to_string(),
+            0,
+            bump.wrapping_sub(1),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::PdaMismatch as u32)
+        )
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_greeting_with_bump(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Hello, Solana!".to_string(),
+            0,
+            bump,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.name, "Alice");
+    assert_eq!(state.bump, bump);
+}
+
+#[tokio::test]
+async fn test_set_visibility_blocks_likes() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    assert!(
+        fetch_greeting(&mut banks_client, greeting_pda)
+            .await
+            .is_public
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_visibility(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            false,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert!(!state.is_public);
+    assert_eq!(state.update_count, 0);
+
+    let like = instruction::like_greeting(&program_id, &payer.pubkey(), &greeting_pda);
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&like),
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::GreetingPrivate as u32)
+        )
+    );
+
+    // Making it public again lets the like through
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::set_visibility(&program_id, &payer.pubkey(), &greeting_pda, true),
+            like,
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        fetch_greeting(&mut banks_client, greeting_pda).await.likes,
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_named_greetings_get_separate_accounts() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::create_named_greeting(
+                &program_id,
+                &payer.pubkey(),
+                "Alice".to_string(),
+                "Hello from Alice".to_string(),
+            ),
+            instruction::create_named_greeting(
+                &program_id,
+                &payer.pubkey(),
+                "Bob".to_string(),
+                "Hello from Bob".to_string(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let alice_pda = derive_named_greeting_pda(&program_id, &payer.pubkey(), "Alice").0;
+    let bob_pda = derive_named_greeting_pda(&program_id, &payer.pubkey(), "Bob").0;
+    assert_ne!(alice_pda, bob_pda);
+    assert_eq!(
+        fetch_greeting(&mut banks_client, alice_pda).await.message,
+        "Hello from Alice"
+    );
+    assert_eq!(
+        fetch_greeting(&mut banks_client, bob_pda).await.message,
+        "Hello from Bob"
+    );
+
+    // The handler checks the passed account really is the PDA for the given name
+    let mut carol = instruction::create_named_greeting(
+        &program_id,
+        &payer.pubkey(),
+        "Carol".to_string(),
+        "Hello from Carol".to_string(),
+    );
+    carol.accounts[1].pubkey = derive_named_greeting_pda(&program_id, &payer.pubkey(), "Dave").0;
+    let transaction = Transaction::new_signed_with_payer(
+        &[carol],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::PdaMismatch as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_batch_set_greeting_updates_every_account() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let names = ["Alice", "Bob", "Carol"];
+    let transaction = Transaction::new_signed_with_payer(
+        &names.map(|name| {
+            instruction::create_named_greeting(
+                &program_id,
+                &payer.pubkey(),
+                name.to_string(),
+                "Hello".to_string(),
+            )
+        }),
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let pdas = names.map(|name| derive_named_greeting_pda(&program_id, &payer.pubkey(), name).0);
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::batch_set_greeting(
+            &program_id,
+            &payer.pubkey(),
+            pdas.iter()
+                .zip(names)
+                .map(|(pda, name)| (*pda, format!("Hi, {}", name)))
+                .collect(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    for (pda, name) in pdas.into_iter().zip(names) {
+        let state = fetch_greeting(&mut banks_client, pda).await;
+        assert_eq!(state.message, format!("Hi, {}", name));
+        assert_eq!(state.update_count, 1);
+    }
+}
+
+#[tokio::test]
+async fn test_set_or_create_greeting_upserts() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda = derive_named_greeting_pda(&program_id, &payer.pubkey(), "Alice").0;
+
+    // The account doesn't exist yet, so the first call creates it
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_or_create_greeting(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "First".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.authority, payer.pubkey());
+    assert_eq!(state.message, "First");
+    assert_eq!(state.update_count, 0);
+
+    // The second call finds it and only updates the message
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_or_create_greeting(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Second".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.message, "Second");
+    assert_eq!(state.update_count, 1);
+    let registry = banks_client
+        .get_account(derive_registry_pda(&program_id, &payer.pubkey()).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unpack_registry(&registry.data).unwrap().greeting_count, 1);
+}
+
+#[tokio::test]
+async fn test_co_authority_requires_both_signers() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let co_authority = Keypair::new();
+
+    // Without a co-authority the authority alone can add one
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_co_authority(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            None,
+            Some(co_authority.pubkey()),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.co_authority, Some(co_authority.pubkey()));
+
+    // From now on the authority's signature alone no longer sets the message
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            "Solo".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_greeting_with_co_authority(
+            &program_id,
+            &payer.pubkey(),
+            &co_authority.pubkey(),
+            &greeting_pda,
+            "Together".to_string(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &co_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        fetch_greeting(&mut banks_client, greeting_pda)
+            .await
+            .message,
+        "Together"
+    );
+
+    // Clearing it takes both signatures too, after which one signer is enough again
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::set_co_authority(
+                &program_id,
+                &payer.pubkey(),
+                &greeting_pda,
+                Some(&co_authority.pubkey()),
+                None,
+            ),
+            instruction::set_greeting(
+                &program_id,
+                &payer.pubkey(),
+                &greeting_pda,
+                "Solo again".to_string(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &co_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.co_authority, None);
+    assert_eq!(state.message, "Solo again");
+}
+
+#[tokio::test]
+async fn test_transfer_authority_needs_both_signers() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let co_authority = Keypair::new();
+    let receiver = Pubkey::new_unique();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_co_authority(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            None,
+            Some(co_authority.pubkey()),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The authority alone can't give the greeting away
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::transfer_authority(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            None,
+            receiver,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+
+    // Nor can both hand it to the co-authority, which would then hold both signatures
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::transfer_authority(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            Some(&co_authority.pubkey()),
+            co_authority.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &co_authority],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::InvalidNewAuthority as u32)
+        )
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::transfer_authority(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            Some(&co_authority.pubkey()),
+            receiver,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &co_authority],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let state = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(state.authority, receiver);
+    assert_eq!(state.co_authority, Some(co_authority.pubkey()));
+}
+
+#[tokio::test]
+async fn test_expired_greeting_can_be_closed_by_anyone() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let authority = Keypair::new();
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(
+                &context.payer.pubkey(),
+                &authority.pubkey(),
+                1_000_000_000,
+            ),
+            instruction::create_greeting_with_expiry(
+                &program_id,
+                &authority.pubkey(),
+                "Alice".to_string(),
+                "Short-lived".to_string(),
+                0,
+                now + 60,
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let greeting_pda = derive_greeting_pda(&program_id, &authority.pubkey(), 0).0;
+    let greeting_lamports = context
+        .banks_client
+        .get_balance(greeting_pda)
+        .await
+        .unwrap();
+
+    // Before expiry only the authority may close it
+    let close_expired = instruction::close_expired_greeting(
+        &program_id,
+        &context.payer.pubkey(),
+        &greeting_pda,
+        &authority.pubkey(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&close_expired),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::InvalidAuthority as u32)
+        )
+    );
+
+    // Warp ahead, then move the clock past `expires_at`. Warping alone advances the slot
+    // but barely moves `unix_timestamp`, which is estimated from genesis.
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    context.warp_to_slot(slot + 1_000).unwrap();
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = now + 60;
+    context.set_sysvar(&clock);
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+
+    // The authority can no longer edit it
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_greeting(
+            &program_id,
+            &authority.pubkey(),
+            &greeting_pda,
+            "Still here".to_string(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        recent_blockhash,
+    );
+    let err = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::Expired as u32)
+        )
+    );
+
+    // But anyone can now close it, with the rent going back to the authority
+    let authority_balance = context
+        .banks_client
+        .get_balance(authority.pubkey())
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[close_expired],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        recent_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(authority.pubkey())
+            .await
+            .unwrap(),
+        authority_balance + greeting_lamports
+    );
+    assert!(context
+        .banks_client
+        .get_account(greeting_pda)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_stats_count_only_when_passed() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let stats_pda = derive_stats_pda(&program_id).0;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            instruction::initialize_stats(&program_id, &payer.pubkey()),
+            // Counted: both instructions carry the stats PDA
+            instruction::with_stats(
+                &program_id,
+                instruction::create_greeting(
+                    &program_id,
+                    &payer.pubkey(),
+                    "Alice".to_string(),
+                    "Hello".to_string(),
+                    0,
+                ),
+            ),
+            instruction::with_stats(
+                &program_id,
+                instruction::set_greeting(
+                    &program_id,
+                    &payer.pubkey(),
+                    &derive_greeting_pda(&program_id, &payer.pubkey(), 0).0,
+                    "Hi".to_string(),
+                ),
+            ),
+            // Not counted: built the way clients predating stats build them
+            instruction::create_greeting(
+                &program_id,
+                &payer.pubkey(),
+                "Bob".to_string(),
+                "Hello".to_string(),
+                1,
+            ),
+            instruction::set_greeting(
+                &program_id,
+                &payer.pubkey(),
+                &derive_greeting_pda(&program_id, &payer.pubkey(), 1).0,
+                "Hey".to_string(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let stats = banks_client.get_account(stats_pda).await.unwrap().unwrap();
+    assert_eq!(
+        unpack_stats(&stats.data).unwrap(),
+        StatsState {
+            total_greetings: 1,
+            total_updates: 1,
+        }
+    );
+    let greeting = fetch_greeting(
+        &mut banks_client,
+        derive_greeting_pda(&program_id, &payer.pubkey(), 1).0,
+    )
+    .await;
+    assert_eq!(greeting.message, "Hey");
+}
+
+#[tokio::test]
+async fn test_donations_accumulate() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let donor = Keypair::new();
+    let before = banks_client.get_balance(greeting_pda).await.unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &donor.pubkey(), 1_000_000_000),
+            instruction::donate_to_greeting(&program_id, &donor.pubkey(), &greeting_pda, 5_000),
+            instruction::donate_to_greeting(&program_id, &payer.pubkey(), &greeting_pda, 2_500),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &donor],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(greeting_pda).await.unwrap(),
+        before + 7_500
+    );
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.total_donations, 7_500);
+    // Donating changes nothing else
+    assert_eq!(greeting.update_count, 0);
+    assert_eq!(greeting.message, "Hello, Solana!");
+}
+
+#[tokio::test]
+async fn test_withdraw_donations_keeps_rent_exemption() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let destination = Pubkey::new_unique();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::donate_to_greeting(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            5_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let rent_exempt = banks_client
+        .get_rent()
+        .await
+        .unwrap()
+        .minimum_balance(GreetingAccountState::get_max_space_needed());
+
+    // Taking one lamport more than was donated would dip into the rent reserve
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::withdraw_donations(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &destination,
+            5_000_001,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(GreetingError::WouldBreakRentExemption as u32)
+        )
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::withdraw_donations(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &destination,
+            3_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(destination).await.unwrap(),
+        3_000_000
+    );
+    assert_eq!(
+        banks_client.get_balance(greeting_pda).await.unwrap(),
+        rent_exempt + 2_000_000
+    );
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.total_donations, 2_000_000);
+}
+
+#[tokio::test]
+async fn test_sweep_surplus_leaves_exactly_rent_exemption() {
+    let program_id = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) = program_test(program_id).start().await;
+    let greeting_pda =
+        create_greeting(&mut banks_client, &program_id, &payer, recent_blockhash).await;
+    let destination = Pubkey::new_unique();
+    let rent_exempt = banks_client
+        .get_rent()
+        .await
+        .unwrap()
+        .minimum_balance(GreetingAccountState::get_max_space_needed());
+    assert_eq!(
+        banks_client.get_balance(greeting_pda).await.unwrap(),
+        rent_exempt
+    );
+
+    // A plain transfer, as from a client that over-funded the account
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &greeting_pda,
+            4_000_000,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::sweep_surplus(
+            &program_id,
+            &payer.pubkey(),
+            &greeting_pda,
+            &destination,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        banks_client.get_balance(destination).await.unwrap(),
+        4_000_000
+    );
+    assert_eq!(
+        banks_client.get_balance(greeting_pda).await.unwrap(),
+        rent_exempt
+    );
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.total_donations, 0);
+    assert_eq!(greeting.message, "Hello, Solana!");
+}
+
+#[tokio::test]
+async fn test_registry_tracks_next_index() {
+    let program_id = Pubkey::new_unique();
+    let payer = Keypair::new();
+    let registry_pda = derive_registry_pda(&program_id, &payer.pubkey()).0;
+    // A registry as created before `next_index` existed, holding one greeting
+    let mut legacy = b"REGISTRY".to_vec();
+    legacy.extend_from_slice(&1u32.to_le_bytes());
+    let mut program_test = program_test(program_id);
+    program_test.add_account(
+        payer.pubkey(),
+        Account::new(10_000_000_000, 0, &solana_sdk::system_program::ID),
+    );
+    program_test.add_account(
+        registry_pda,
+        Account {
+            lamports: Rent::default().minimum_balance(legacy.len()),
+            data: legacy,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let (banks_client, _, recent_blockhash) = program_test.start().await;
+
+    let create = |index: u64| {
+        instruction::create_greeting(
+            &program_id,
+            &payer.pubkey(),
+            "Alice".to_string(),
+            "Hello".to_string(),
+            index,
+        )
+    };
+    let transaction = Transaction::new_signed_with_payer(
+        &[create(3), create(1)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let registry = banks_client
+        .get_account(registry_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(registry.data.len(), 20);
+    assert!(registry.lamports >= Rent::default().minimum_balance(registry.data.len()));
+    let registry = unpack_registry(&registry.data).unwrap();
+    assert_eq!(registry.greeting_count, 3);
+    assert_eq!(registry.next_index, 4);
+
+    // Enumerating up to `next_index` finds exactly the greetings created
+    let mut found = vec![];
+    for pda in instruction::greeting_pdas_for(&program_id, &payer.pubkey(), registry.next_index) {
+        if banks_client.get_account(pda).await.unwrap().is_some() {
+            found.push(pda);
+        }
+    }
+    assert_eq!(
+        found,
+        vec![
+            derive_greeting_pda(&program_id, &payer.pubkey(), 1).0,
+            derive_greeting_pda(&program_id, &payer.pubkey(), 3).0,
+        ]
+    );
+}
+
+const VAULT_SEED: &[u8] = b"vault";
+
+// A minimal program that creates greetings from its own PDA, the way a composing
+// program would. Accounts: vault, greeting, system program, registry, config,
+// greeting program.
+fn process_wrapper(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let (_, bump) = Pubkey::find_program_address(&[VAULT_SEED], program_id);
+    cpi::create_greeting(
+        cpi::CreateGreetingCpiAccounts {
+            payer: &accounts[0],
+            greeting: &accounts[1],
+            system_program: &accounts[2],
+            registry: &accounts[3],
+            config: &accounts[4],
+            greeting_program: &accounts[5],
+        },
+        "Vault".to_string(),
+        "Hello via CPI".to_string(),
+        0,
+        &[&[VAULT_SEED, &[bump]]],
+    )
+}
+
+#[tokio::test]
+async fn test_wrapper_program_creates_greeting_via_cpi() {
+    let program_id = Pubkey::new_unique();
+    let wrapper_id = Pubkey::new_unique();
+    let (vault, _) = Pubkey::find_program_address(&[VAULT_SEED], &wrapper_id);
+    let mut program_test = program_test(program_id);
+    program_test.add_program("greeting_wrapper", wrapper_id, processor!(process_wrapper));
+    program_test.add_account(
+        vault,
+        Account::new(1_000_000_000, 0, &solana_sdk::system_program::ID),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let greeting_pda = derive_greeting_pda(&program_id, &vault, 0).0;
+    let wrapper_ix = Instruction::new_with_bytes(
+        wrapper_id,
+        &[],
+        vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(greeting_pda, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+            AccountMeta::new(derive_registry_pda(&program_id, &vault).0, false),
+            AccountMeta::new_readonly(derive_config_pda(&program_id).0, false),
+            AccountMeta::new_readonly(program_id, false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[wrapper_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The vault PDA signed through the wrapper's seeds, so it owns the greeting
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.authority, vault);
+    assert_eq!(greeting.message, "Hello via CPI");
+}
+
+const CONTROLLER_SEED: &[u8] = b"controller";
+
+// A program that fully controls its greetings through a PDA authority. Instruction data
+// `[0]` creates greeting 0 (accounts: payer, authority, greeting, system program,
+// registry, config, greeting program); `[1, message..]` sets its message (accounts:
+// authority, greeting, config, greeting program). Either way it signs for the authority.
+fn process_controller(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (_, bump) = Pubkey::find_program_address(&[CONTROLLER_SEED], program_id);
+    let signer_seeds: &[&[&[u8]]] = &[&[CONTROLLER_SEED, &[bump]]];
+    match instruction_data {
+        [0] => cpi::create_greeting_with_pda_authority(
+            cpi::CreateGreetingWithPdaAuthorityCpiAccounts {
+                payer: &accounts[0],
+                authority: &accounts[1],
+                greeting: &accounts[2],
+                system_program: &accounts[3],
+                registry: &accounts[4],
+                config: &accounts[5],
+                greeting_program: &accounts[6],
+            },
+            "Managed".to_string(),
+            "Hello from a program".to_string(),
+            0,
+            signer_seeds,
+        ),
+        [1, message @ ..] => invoke_signed(
+            &instruction::set_greeting(
+                accounts[3].key,
+                accounts[0].key,
+                accounts[1].key,
+                String::from_utf8(message.to_vec()).unwrap(),
+            ),
+            accounts,
+            signer_seeds,
+        ),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+#[tokio::test]
+async fn test_controller_program_owns_greeting_through_pda_authority() {
+    let program_id = Pubkey::new_unique();
+    let controller_id = Pubkey::new_unique();
+    let (authority, _) = Pubkey::find_program_address(&[CONTROLLER_SEED], &controller_id);
+    let mut program_test = program_test(program_id);
+    program_test.add_program(
+        "greeting_controller",
+        controller_id,
+        processor!(process_controller),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // The payer funds the greeting, but the authority, which holds no lamports, owns it
+    let greeting_pda = derive_greeting_pda(&program_id, &authority, 0).0;
+    let config_pda = derive_config_pda(&program_id).0;
+    let create_ix = Instruction::new_with_bytes(
+        controller_id,
+        &[0],
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(authority, false),
+            AccountMeta::new(greeting_pda, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+            AccountMeta::new(derive_registry_pda(&program_id, &authority).0, false),
+            AccountMeta::new_readonly(config_pda, false),
+            AccountMeta::new_readonly(program_id, false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[create_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.authority, authority);
+    let registry = banks_client
+        .get_account(derive_registry_pda(&program_id, &authority).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unpack_registry(&registry.data).unwrap().greeting_count, 1);
+
+    // Updates go through the controller, which signs for the authority again
+    let set_ix = Instruction::new_with_bytes(
+        controller_id,
+        &[&[1], "Updated by program".as_bytes()].concat(),
+        vec![
+            AccountMeta::new_readonly(authority, false),
+            AccountMeta::new(greeting_pda, false),
+            AccountMeta::new_readonly(config_pda, false),
+            AccountMeta::new_readonly(program_id, false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[set_ix],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let greeting = fetch_greeting(&mut banks_client, greeting_pda).await;
+    assert_eq!(greeting.message, "Updated by program");
+
+    // A wallet can't take the PDA authority role, even one that signs
+    let wallet = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_greeting_with_pda_authority(
+            &program_id,
+            &payer.pubkey(),
+            &wallet.pubkey(),
+            "Mine".to_string(),
+            "Hello".to_string(),
+            0,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &wallet],
+        recent_blockhash,
+    );
+    let err = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::InvalidArgument)
+    );
+}